@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::output::{prepare_output, OutputNaming};
+use crate::transcript::{Segment, Transcript};
+
+/// Script resolution used when the source has no video stream (or it cannot be probed).
+pub const DEFAULT_RESOLUTION: (u32, u32) = (1920, 1080);
+
+/// `[V4+ Styles]` settings for the generated `Default` style, persisted as the `ass` selection.
+///
+/// Sizes and margins are in script pixels; left unset they scale with the video height.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssStyle {
+    pub font_name: String,
+    pub font_size: Option<u32>,
+    pub bold: bool,
+    /// `#RRGGBB` or `#RRGGBBAA`.
+    pub primary_color: String,
+    /// Colour words fade from before karaoke highlights them.
+    pub secondary_color: String,
+    pub outline_color: String,
+    pub back_color: String,
+    pub outline: f32,
+    pub shadow: f32,
+    /// Numpad layout: 1-3 bottom, 4-6 middle, 7-9 top.
+    pub alignment: u8,
+    pub margin_l: Option<u32>,
+    pub margin_r: Option<u32>,
+    pub margin_v: Option<u32>,
+    /// Emit `\k` tags so each word is highlighted as it is spoken.
+    pub karaoke: bool,
+}
+
+impl Default for AssStyle {
+    fn default() -> Self {
+        AssStyle {
+            font_name: "Arial".to_string(),
+            font_size: None,
+            bold: false,
+            primary_color: "#FFFFFF".to_string(),
+            secondary_color: "#FFD700".to_string(),
+            outline_color: "#000000".to_string(),
+            back_color: "#00000080".to_string(),
+            outline: 2.0,
+            shadow: 0.0,
+            alignment: 2,
+            margin_l: None,
+            margin_r: None,
+            margin_v: None,
+            karaoke: false,
+        }
+    }
+}
+
+impl AssStyle {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.font_name.trim().is_empty() || self.font_name.contains(',') {
+            return Err(format!("Invalid ASS font name: {}", self.font_name));
+        }
+        if self.font_size == Some(0) {
+            return Err("ASS font size must be greater than 0".to_string());
+        }
+        if !(1..=9).contains(&self.alignment) {
+            return Err(format!("ASS alignment must be between 1 and 9, got {}", self.alignment));
+        }
+        if self.outline < 0.0 || self.shadow < 0.0 {
+            return Err("ASS outline and shadow cannot be negative".to_string());
+        }
+        for color in [&self.primary_color, &self.secondary_color, &self.outline_color, &self.back_color] {
+            ass_color(color)?;
+        }
+        Ok(())
+    }
+
+    fn style_line(&self, (width, height): (u32, u32)) -> Result<String, String> {
+        let font_size = self.font_size.unwrap_or_else(|| scaled(height, 0.055));
+        let side_margin = scaled(width, 0.05);
+
+        Ok(format!(
+            "Style: Default,{},{},{},{},{},{},{},0,0,0,100,100,0,0,1,{},{},{},{},{},{},1",
+            self.font_name.trim(),
+            font_size,
+            ass_color(&self.primary_color)?,
+            ass_color(&self.secondary_color)?,
+            ass_color(&self.outline_color)?,
+            ass_color(&self.back_color)?,
+            if self.bold { -1 } else { 0 },
+            self.outline,
+            self.shadow,
+            self.alignment,
+            self.margin_l.unwrap_or(side_margin),
+            self.margin_r.unwrap_or(side_margin),
+            self.margin_v.unwrap_or_else(|| scaled(height, 0.04)),
+        ))
+    }
+}
+
+pub fn create_ass(
+    transcript: &Transcript,
+    style: &AssStyle,
+    resolution: (u32, u32),
+    naming: &OutputNaming,
+    app: AppHandle,
+) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, "ass", &app) else {
+        return Ok(String::new());
+    };
+
+    let script = render_ass(transcript, style, resolution)?;
+    let mut file = File::create(&file_path_buf).map_err(|e| format!("Failed to create ASS file: {}", e))?;
+    file.write_all(script.as_bytes())
+        .map_err(|e| format!("Failed to write to ASS file: {}", e))?;
+
+    app.emit("subtitle_created", format!("ASS Created :: {}", file_path_buf.display()))
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+
+    Ok(file_path_buf.to_string_lossy().to_string())
+}
+
+/// Renders a transcript's segments as an Advanced SubStation Alpha script.
+pub fn render_ass(transcript: &Transcript, style: &AssStyle, resolution: (u32, u32)) -> Result<String, String> {
+    let (width, height) = resolution;
+    let mut script = format!(
+        "[Script Info]\n\
+         ; Generated by SRTify\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {}\n\
+         PlayResY: {}\n\
+         WrapStyle: 0\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+         Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, \
+         Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         {}\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        width,
+        height,
+        style.style_line(resolution)?
+    );
+
+    for segment in transcript.cues() {
+        let body = if style.karaoke && !segment.words.is_empty() {
+            karaoke_text(segment)
+        } else {
+            segment.render_styled(
+                |style| format!("{{\\{}1}}", style.code()),
+                |style| format!("{{\\{}0}}", style.code()),
+                escape_text,
+            )
+        };
+
+        script.push_str(&format!(
+            "Dialogue: 0,{},{},Default,{},0,0,0,,{}\n",
+            ms_to_ass_time(segment.start_ms),
+            ms_to_ass_time(segment.end_ms),
+            segment.speaker.as_deref().map(|speaker| speaker.replace(',', " ")).unwrap_or_default(),
+            body
+        ));
+    }
+
+    Ok(script)
+}
+
+/// Builds `{\k30}Hello {\k45}world` where each duration (in centiseconds) runs until
+/// the next word starts; silences become their own empty `\k` syllables. The cue's line
+/// breaks are kept as `\N`.
+fn karaoke_text(segment: &Segment) -> String {
+    let breaks = segment.line_breaks();
+    let mut text = String::new();
+    let mut cursor = centiseconds(segment.start_ms);
+    let mut previous = None;
+
+    for (index, word) in segment.words.iter().enumerate() {
+        let word_text = word.text.trim();
+        if word_text.is_empty() {
+            continue;
+        }
+        let start = centiseconds(word.start_ms).max(cursor);
+        let end = centiseconds(word.end_ms).max(start);
+
+        if start > cursor {
+            text.push_str(&format!("{{\\k{}}}", start - cursor));
+        }
+        match previous {
+            Some(previous) if breaks.contains(&previous) => text.push_str("\\N"),
+            _ if !text.is_empty() => text.push(' '),
+            _ => {}
+        }
+        text.push_str(&format!("{{\\k{}}}{}", end - start, escape_text(word_text)));
+        cursor = end;
+        previous = Some(index);
+    }
+
+    text
+}
+
+/// Converts `#RRGGBB` / `#RRGGBBAA` into ASS's `&HAABBGGRR`, where alpha 00 is opaque.
+fn ass_color(color: &str) -> Result<String, String> {
+    let hex = color.trim().trim_start_matches('#');
+    let invalid = || format!("Invalid colour {}, expected #RRGGBB or #RRGGBBAA", color);
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let (red, green, blue) = (channel(0)?, channel(2)?, channel(4)?);
+    let alpha = if hex.len() == 8 { 255 - channel(6)? } else { 0 };
+
+    Ok(format!("&H{:02X}{:02X}{:02X}{:02X}", alpha, blue, green, red))
+}
+
+fn ms_to_ass_time(ms: u64) -> String {
+    let total_cs = centiseconds(ms);
+    let hours = total_cs / 360_000;
+    let minutes = (total_cs % 360_000) / 6000;
+    let seconds = (total_cs % 6000) / 100;
+    let hundredths = total_cs % 100;
+
+    format!("{}:{:02}:{:02}.{:02}", hours, minutes, seconds, hundredths)
+}
+
+fn centiseconds(ms: u64) -> u64 {
+    (ms + 5) / 10
+}
+
+fn scaled(dimension: u32, factor: f64) -> u32 {
+    ((dimension as f64 * factor).round() as u32).max(1)
+}
+
+/// Braces start override blocks and backslashes start tags, so neither may appear verbatim.
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\u{29F5}")
+        .replace('{', "(")
+        .replace('}', ")")
+        .replace('\n', "\\N")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{StyleSpan, TextStyle, Word};
+
+    fn word(text: &str, start_ms: u64, end_ms: u64) -> Word {
+        Word { text: text.to_string(), start_ms, end_ms, ..Default::default() }
+    }
+
+    #[test]
+    fn karaoke_text_keeps_line_breaks() {
+        let segment = Segment {
+            start_ms: 1000,
+            end_ms: 3000,
+            text: "Hello there\nworld".to_string(),
+            words: vec![word("Hello", 1000, 1400), word(" there", 1500, 1900), word("world", 2000, 2500)],
+            ..Default::default()
+        };
+        assert_eq!(karaoke_text(&segment), "{\\k40}Hello{\\k10} {\\k40}there{\\k10}\\N{\\k50}world");
+    }
+
+    #[test]
+    fn styling_becomes_override_tags() {
+        let segment = Segment {
+            start_ms: 0,
+            end_ms: 1000,
+            text: "Hello {there}".to_string(),
+            styles: vec![StyleSpan { start: 0, end: 5, style: TextStyle::Italic }],
+            ..Default::default()
+        };
+        let script = render_ass(&Transcript::new(vec![segment]), &AssStyle::default(), DEFAULT_RESOLUTION).unwrap();
+        assert!(script.contains(",,{\\i1}Hello{\\i0} (there)\n"), "{}", script);
+    }
+}
@@ -0,0 +1,364 @@
+use hound::{SampleFormat, WavReader};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::{Hint, ProbeResult};
+
+use crate::job::{JobHandle, CANCELLED_MESSAGE};
+
+/// Sample rate whisper.cpp expects for its input PCM.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Decodes a WAV file into 16 kHz mono f32 samples ready for whisper.
+///
+/// Multi-channel audio is averaged down to mono and any other sample rate is
+/// resampled, so callers never have to care what ffmpeg (or the user) produced.
+pub fn read_wav_samples(file_path: &str) -> Result<Vec<f32>, String> {
+    let mut reader = WavReader::open(file_path).map_err(|e| match e {
+        hound::Error::Unsupported => format!(
+            "Unsupported WAV encoding in {}: only integer PCM and 32-bit float are supported",
+            file_path
+        ),
+        hound::Error::FormatError(msg) => format!("Invalid WAV file {}: {}", file_path, msg),
+        other => format!("Failed to open WAV file {}: {}", file_path, other),
+    })?;
+    let spec = reader.spec();
+
+    if spec.channels == 0 {
+        return Err(format!("Invalid WAV file {}: no audio channels", file_path));
+    }
+    if spec.sample_rate == 0 {
+        return Err(format!("Invalid WAV file {}: sample rate is 0 Hz", file_path));
+    }
+
+    let interleaved: Result<Vec<f32>, hound::Error> = match (spec.sample_format, spec.bits_per_sample) {
+        (SampleFormat::Int, bits @ (8 | 16 | 24 | 32)) => {
+            let scale = (1i64 << (bits - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|value| value as f32 / scale))
+                .collect()
+        }
+        (SampleFormat::Float, 32) => reader.samples::<f32>().collect(),
+        (format, bits) => {
+            return Err(format!(
+                "Unsupported WAV sample format in {}: {:?} {}-bit",
+                file_path, format, bits
+            ))
+        }
+    };
+    let interleaved = interleaved.map_err(|e| format!("Failed to decode WAV samples: {}", e))?;
+
+    let mono = downmix_to_mono(&interleaved, spec.channels as usize);
+    Ok(resample(&mono, spec.sample_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Decodes the first audio track of a media file into 16 kHz mono f32 samples
+/// without going through ffmpeg.
+///
+/// Covers WAV, FLAC, MP3, OGG/Vorbis, ALAC and AAC in MP4/M4A/MOV as well as MKV/WebM
+/// with one of those codecs. Anything else returns an error so the caller can fall
+/// back to ffmpeg.
+pub fn decode_media(file_path: &str, job: &JobHandle) -> Result<Vec<f32>, String> {
+    let mut format = probe_media(file_path)?.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| format!("No audio track found in {}", file_path))?;
+    let track_id = track.id;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported codec: {}", e))?;
+
+    let mut mono = Vec::new();
+    let mut sample_rate = track.codec_params.sample_rate;
+    let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        if job.is_cancelled() {
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(format!("Failed to read packet: {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame is not worth failing the whole file over
+            Err(SymphoniaError::DecodeError(e)) => {
+                eprintln!("Skipping undecodable packet: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        sample_rate = Some(spec.rate);
+
+        let required = decoded.capacity() * channels;
+        let buffer = match sample_buffer.as_mut() {
+            Some(buffer) if buffer.capacity() >= required => buffer,
+            _ => sample_buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        mono.extend(downmix_to_mono(buffer.samples(), channels));
+    }
+
+    let sample_rate = sample_rate.ok_or_else(|| format!("Unknown sample rate in {}", file_path))?;
+    if mono.is_empty() {
+        return Err(format!("No audio could be decoded from {}", file_path));
+    }
+
+    Ok(resample(&mono, sample_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Descriptive tags of a media file, as far as its container carries them.
+#[derive(Debug, Clone, Default)]
+pub struct MediaTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+/// Reads title, artist and album tags (ID3, Vorbis comments, MP4 atoms, ...).
+///
+/// Tags found in the container itself take precedence over ones read while probing,
+/// such as an ID3v2 header in front of an MP3 stream.
+pub fn read_media_tags(file_path: &str) -> MediaTags {
+    let mut tags = MediaTags::default();
+    let Ok(mut probed) = probe_media(file_path) else {
+        return tags;
+    };
+
+    if let Some(revision) = probed.format.metadata().skip_to_latest() {
+        merge_tags(&mut tags, revision);
+    }
+    if let Some(revision) = probed.metadata.get().as_mut().and_then(|m| m.skip_to_latest()) {
+        merge_tags(&mut tags, revision);
+    }
+    tags
+}
+
+/// Length of the first audio track in seconds, from the container's frame count.
+///
+/// Returns `None` when the container does not record it (e.g. some streamed MP3s).
+pub fn get_audio_duration(file_path: &str) -> Option<f64> {
+    let probed = probe_media(file_path).ok()?;
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)?;
+    let params = &track.codec_params;
+    let n_frames = params.n_frames?;
+
+    match (params.time_base, params.sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(n_frames);
+            Some(time.seconds as f64 + time.frac)
+        }
+        (None, Some(sample_rate)) if sample_rate > 0 => Some(n_frames as f64 / sample_rate as f64),
+        _ => None,
+    }
+}
+
+fn probe_media(file_path: &str) -> Result<ProbeResult, String> {
+    let file = File::open(file_path).map_err(|e| format!("Failed to open {}: {}", file_path, e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(file_path).extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Unsupported container: {}", e))
+}
+
+fn merge_tags(tags: &mut MediaTags, revision: &MetadataRevision) {
+    for tag in revision.tags() {
+        let slot = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => &mut tags.title,
+            Some(StandardTagKey::Artist) => &mut tags.artist,
+            Some(StandardTagKey::AlbumArtist) if tags.artist.is_none() => &mut tags.artist,
+            Some(StandardTagKey::Album) => &mut tags.album,
+            _ => continue,
+        };
+        let value = tag.value.to_string();
+        if slot.is_none() && !value.trim().is_empty() {
+            *slot = Some(value.trim().to_string());
+        }
+    }
+}
+
+/// Averages interleaved frames into a single channel.
+pub fn downmix_to_mono(samples: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return samples.to_vec();
+    }
+
+    samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Converts mono samples between sample rates.
+///
+/// Downsampling averages the input samples each output sample covers, which is
+/// enough of a low-pass to keep speech free of audible aliasing; upsampling
+/// interpolates linearly.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let output_len = (samples.len() as f64 / ratio).floor() as usize;
+    let mut output = Vec::with_capacity(output_len);
+
+    for i in 0..output_len {
+        let position = i as f64 * ratio;
+        let index = position.floor() as usize;
+
+        if ratio > 1.0 {
+            let end = ((position + ratio).ceil() as usize).min(samples.len());
+            let window = &samples[index..end.max(index + 1)];
+            output.push(window.iter().sum::<f32>() / window.len() as f32);
+        } else {
+            let fraction = (position - index as f64) as f32;
+            let current = samples[index];
+            let next = samples.get(index + 1).copied().unwrap_or(current);
+            output.push(current + (next - current) * fraction);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{WavSpec, WavWriter};
+    use std::path::PathBuf;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("srtify-audio-test-{}-{}", std::process::id(), name))
+    }
+
+    fn write_wav(name: &str, spec: WavSpec, write: impl FnOnce(&mut WavWriter<std::io::BufWriter<File>>)) -> PathBuf {
+        let path = scratch_path(name);
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        write(&mut writer);
+        writer.finalize().unwrap();
+        path
+    }
+
+    /// A canonical 44 byte header for the given format tag, with no samples.
+    fn wav_header(format_tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut header = Vec::new();
+        header.extend(b"RIFF");
+        header.extend(36u32.to_le_bytes());
+        header.extend(b"WAVEfmt ");
+        header.extend(16u32.to_le_bytes());
+        header.extend(format_tag.to_le_bytes());
+        header.extend(channels.to_le_bytes());
+        header.extend(sample_rate.to_le_bytes());
+        header.extend((sample_rate * block_align as u32).to_le_bytes());
+        header.extend(block_align.to_le_bytes());
+        header.extend(bits.to_le_bytes());
+        header.extend(b"data");
+        header.extend(0u32.to_le_bytes());
+        header
+    }
+
+    #[test]
+    fn wav_files_become_16_khz_mono() {
+        let stereo = WavSpec {
+            channels: 2,
+            sample_rate: WHISPER_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let path = write_wav("stereo.wav", stereo, |writer| {
+            for _ in 0..4 {
+                writer.write_sample(16384i16).unwrap();
+                writer.write_sample(0i16).unwrap();
+            }
+        });
+        assert_eq!(read_wav_samples(&path.to_string_lossy()).unwrap(), [0.25; 4]);
+        std::fs::remove_file(&path).ok();
+
+        let float = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let path = write_wav("float.wav", float, |writer| {
+            for sample in [0.0f32, 0.5] {
+                writer.write_sample(sample).unwrap();
+            }
+        });
+        assert_eq!(read_wav_samples(&path.to_string_lossy()).unwrap(), [0.0, 0.25, 0.5, 0.5]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn unusable_wav_files_are_rejected() {
+        let cases = [
+            ("garbage.wav", b"not a wav file at all".to_vec(), "Invalid WAV file"),
+            ("silent.wav", wav_header(1, 1, 0, 16), "sample rate is 0 Hz"),
+            ("alaw.wav", wav_header(6, 1, 8000, 8), "Unsupported WAV encoding"),
+        ];
+        for (name, content, error) in cases {
+            let path = scratch_path(name);
+            std::fs::write(&path, content).unwrap();
+            let result = read_wav_samples(&path.to_string_lossy());
+            assert!(result.as_ref().is_err_and(|e| e.contains(error)), "{}: {:?}", name, result);
+            std::fs::remove_file(&path).ok();
+        }
+
+        let missing = scratch_path("missing.wav");
+        assert!(read_wav_samples(&missing.to_string_lossy()).is_err_and(|e| e.starts_with("Failed to open WAV file")));
+    }
+
+    #[test]
+    fn frames_are_averaged_into_one_channel() {
+        assert_eq!(downmix_to_mono(&[1.0, 0.0, 0.5, 0.5, -1.0, 1.0], 2), [0.5, 0.5, 0.0]);
+        // A trailing partial frame is dropped
+        assert_eq!(downmix_to_mono(&[0.3, 0.3, 0.3, 0.9], 3), [0.3]);
+        assert_eq!(downmix_to_mono(&[0.1, 0.2], 1), [0.1, 0.2]);
+    }
+
+    #[test]
+    fn resampling_averages_down_and_interpolates_up() {
+        assert_eq!(resample(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0], 48000, 16000), [1.0, 4.0]);
+        assert_eq!(resample(&[0.0, 1.0], 8000, 16000), [0.0, 0.5, 1.0, 1.0]);
+        assert_eq!(resample(&[0.1, 0.2], 16000, 16000), [0.1, 0.2]);
+
+        let second: Vec<f32> = (0..44100).map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / 44100.0).sin()).collect();
+        let resampled = resample(&second, 44100, WHISPER_SAMPLE_RATE);
+        assert_eq!(resampled.len(), WHISPER_SAMPLE_RATE as usize);
+        assert!(resampled.iter().all(|sample| sample.abs() <= 1.0));
+    }
+}
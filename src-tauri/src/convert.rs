@@ -0,0 +1,533 @@
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::ass::{render_ass, AssStyle, DEFAULT_RESOLUTION};
+use crate::audio::MediaTags;
+use crate::import::{import_file_with_warnings, parse_timestamp};
+use crate::lrc::{render_lrc, LrcOptions};
+use crate::retime::{retime_segments, Anchor, RetimeOptions};
+use crate::output::{parse_output_formats, CollisionPolicy, OutputNaming, DEFAULT_NAMING_TEMPLATE};
+use crate::timing::sanitize_cues;
+use crate::text_export::{render_transcript, TranscriptFormat, TranscriptOptions};
+use crate::transcript::{Segment, Transcript};
+use crate::ttml::{render_ttml, FrameRate, TtmlProfile, DEFAULT_FRAME_RATE};
+use crate::utils::{emit_error, json_selection, render_srt, SelectedData};
+use crate::vtt::{render_vtt, VttOptions};
+
+const CLI_USAGE: &str = "Usage: srtify convert <input> --to <format>[,<format>...] [--out <folder>] [--fps <rate>]
+       [--shift [-]<time>] [--convert-fps <from>:<to>] [--anchor <old>=<new> --anchor <old>=<new>]";
+
+/// Naming template for retimed files when none is configured, so they never replace their input.
+const RETIMED_NAMING_TEMPLATE: &str = "{stem}.retimed";
+
+/// Where a converted file was written and what the target format could not keep.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversionReport {
+    pub format: String,
+    /// Empty when an existing file was kept because of the collision policy.
+    pub output: String,
+    pub warnings: Vec<String>,
+}
+
+/// Writer settings for a conversion, taken from the same selections a transcription uses.
+#[derive(Debug, Clone)]
+pub struct ConversionSettings {
+    pub naming_template: String,
+    pub collision_policy: CollisionPolicy,
+    pub vtt_options: VttOptions,
+    pub ass_style: AssStyle,
+    pub transcript_options: TranscriptOptions,
+    pub lrc_options: LrcOptions,
+    /// Frame rate for TTML timing; imported subtitles carry no video to probe.
+    pub frame_rate: f64,
+    pub retiming: Option<RetimeOptions>,
+}
+
+impl ConversionSettings {
+    pub fn from_selections() -> ConversionSettings {
+        let selections = SelectedData::load().unwrap_or_default();
+        ConversionSettings {
+            naming_template: selections
+                .get("naming")
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| DEFAULT_NAMING_TEMPLATE.to_string()),
+            collision_policy: selections
+                .get("collision")
+                .ok()
+                .flatten()
+                .and_then(|policy| CollisionPolicy::parse(&policy))
+                .unwrap_or(CollisionPolicy::Overwrite),
+            vtt_options: json_selection(&selections, "vtt"),
+            ass_style: json_selection(&selections, "ass"),
+            transcript_options: json_selection(&selections, "transcript"),
+            lrc_options: json_selection(&selections, "lrc"),
+            frame_rate: DEFAULT_FRAME_RATE,
+            retiming: None,
+        }
+    }
+
+    /// Retimes cues on the way through; with the default naming the output gets a `.retimed` suffix.
+    pub fn with_retiming(mut self, options: RetimeOptions) -> Result<ConversionSettings, String> {
+        options.validate()?;
+        if self.naming_template == DEFAULT_NAMING_TEMPLATE {
+            self.naming_template = RETIMED_NAMING_TEMPLATE.to_string();
+        }
+        self.retiming = Some(options);
+        Ok(self)
+    }
+}
+
+#[tauri::command]
+pub async fn convert_subtitle(
+    app: AppHandle,
+    input: String,
+    format: String,
+    output_folder: Option<String>,
+    frame_rate: Option<f64>,
+) -> Result<ConversionReport, String> {
+    let mut settings = ConversionSettings::from_selections();
+    if let Some(frame_rate) = frame_rate.filter(|fps| *fps > 0.0) {
+        settings.frame_rate = frame_rate;
+    }
+
+    let report = convert_file(Path::new(&input), &[format], output_folder.as_deref().map(Path::new), &settings)
+        .map(|mut reports| reports.remove(0))
+        .inspect_err(|e| emit_error(&app, e))?;
+    emit_report(&app, &input, &report);
+    Ok(report)
+}
+
+/// Shifts, stretches or frame-rate converts an existing subtitle file, keeping its format.
+#[tauri::command]
+pub async fn retime_subtitle(
+    app: AppHandle,
+    input: String,
+    options: RetimeOptions,
+    output_folder: Option<String>,
+) -> Result<ConversionReport, String> {
+    let path = Path::new(&input);
+    let format = match path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+        Some("vtt") => "vtt",
+        Some("ass" | "ssa") => "ass",
+        Some("json") => "json",
+        _ => "srt",
+    };
+
+    let report = ConversionSettings::from_selections()
+        .with_retiming(options)
+        .and_then(|settings| {
+            convert_file(path, &[format.to_string()], output_folder.as_deref().map(Path::new), &settings)
+        })
+        .map(|mut reports| reports.remove(0))
+        .inspect_err(|e| emit_error(&app, e))?;
+    emit_report(&app, &input, &report);
+    Ok(report)
+}
+
+fn emit_report(app: &AppHandle, input: &str, report: &ConversionReport) {
+    for warning in &report.warnings {
+        app.emit("info", format!("Warning: {}", warning)).unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+    }
+    let message = if report.output.is_empty() {
+        ("info", format!("Skipped converting {}: output already exists", input))
+    } else {
+        ("subtitle_created", format!("Converted :: {}", report.output))
+    };
+    app.emit(message.0, message.1).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+}
+
+/// Reads `input` (SRT, WebVTT, ASS or JSON), renders it as each of `formats` and
+/// writes the files next to the input or into `output_folder`, named by the configured
+/// template. Returns one report per format, in order.
+pub fn convert_file(
+    input: &Path,
+    formats: &[String],
+    output_folder: Option<&Path>,
+    settings: &ConversionSettings,
+) -> Result<Vec<ConversionReport>, String> {
+    let (mut transcript, mut warnings) = import_file_with_warnings(input)?;
+    if let Some(retiming) = &settings.retiming {
+        let dropped = retime_segments(&mut transcript.segments, retiming);
+        sanitize_cues(&mut transcript.segments);
+        if dropped > 0 {
+            warnings.push(format!("{} cue(s) ended before the start after retiming and were dropped", dropped));
+        }
+    }
+
+    // Everything is rendered before anything is written, so a bad format leaves no partial set behind
+    let mut outputs = Vec::new();
+    for format in formats {
+        let format = format.trim().to_lowercase();
+        let (content, extension) = render(&transcript, &format, settings)?;
+        outputs.push((format, content, extension));
+    }
+
+    let folder = output_folder
+        .map(Path::to_path_buf)
+        .or_else(|| input.parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    if !folder.as_os_str().is_empty() {
+        fs::create_dir_all(&folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+    }
+    // JSON written by a transcription names the original media, so outputs line up with it
+    let source = transcript.source.clone().unwrap_or_else(|| input.to_string_lossy().to_string());
+    let extensions: Vec<&str> = outputs.iter().map(|(_, _, extension)| *extension).collect();
+    let naming = OutputNaming::new(
+        &folder.to_string_lossy(),
+        &settings.naming_template,
+        &source,
+        transcript.language.as_deref().unwrap_or("und"),
+        transcript.model.as_deref().unwrap_or_default(),
+        settings.collision_policy,
+        &extensions,
+    );
+
+    let mut targets = Vec::new();
+    for (format, _, extension) in &outputs {
+        let path = naming.resolve(extension);
+        if path.as_deref().is_some_and(|path| is_same_file(path, input)) {
+            return Err(format!("Converting {} to {} would overwrite the input file", input.display(), format));
+        }
+        targets.push(path);
+    }
+
+    let mut reports = Vec::new();
+    for ((format, content, _), path) in outputs.into_iter().zip(targets) {
+        let mut report_warnings = warnings.clone();
+        report_warnings.extend(lossy_warnings(&transcript, &format, settings));
+        let output = match path {
+            Some(path) => {
+                fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                path.to_string_lossy().to_string()
+            }
+            None => String::new(),
+        };
+        reports.push(ConversionReport {
+            format,
+            output,
+            warnings: report_warnings,
+        });
+    }
+    Ok(reports)
+}
+
+/// Renders a transcript as one of the output formats, returning the content and file extension.
+pub fn render(transcript: &Transcript, format: &str, settings: &ConversionSettings) -> Result<(String, &'static str), String> {
+    let frame_rate = FrameRate::from_fps(settings.frame_rate);
+    let rendered = match format {
+        "srt" => (render_srt(transcript), "srt"),
+        "vtt" => (render_vtt(transcript, &settings.vtt_options), "vtt"),
+        "ass" => (render_ass(transcript, &settings.ass_style, DEFAULT_RESOLUTION)?, "ass"),
+        "ttml" => (render_ttml(transcript, TtmlProfile::Imsc1, frame_rate), TtmlProfile::Imsc1.extension()),
+        "ebu-tt-d" => (render_ttml(transcript, TtmlProfile::EbuTtD, frame_rate), TtmlProfile::EbuTtD.extension()),
+        "lrc" => {
+            let tags = MediaTags {
+                title: transcript
+                    .source
+                    .as_deref()
+                    .and_then(|source| Path::new(source).file_stem())
+                    .map(|stem| stem.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            (render_lrc(transcript, &settings.lrc_options, &tags), "lrc")
+        }
+        "json" => (
+            serde_json::to_string_pretty(transcript).map_err(|e| format!("Failed to serialize transcript: {}", e))?,
+            "words.json",
+        ),
+        other => match TranscriptFormat::parse(other) {
+            Some(format) => (render_transcript(transcript, format, &settings.transcript_options), format.extension()),
+            None => return Err(format!("Unknown output format: {}", other)),
+        },
+    };
+    Ok(rendered)
+}
+
+/// Lists what `format` drops or rounds compared to what the transcript carries.
+fn lossy_warnings(transcript: &Transcript, format: &str, settings: &ConversionSettings) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let segments = &transcript.segments;
+
+    let keeps_words = match format {
+        "json" => true,
+        "vtt" => settings.vtt_options.word_timestamps,
+        "ass" => settings.ass_style.karaoke,
+        "lrc" => settings.lrc_options.word_timing,
+        _ => false,
+    };
+    if !keeps_words && segments.iter().any(|segment| !segment.words.is_empty()) {
+        warnings.push(format!("Word timing is dropped in {}", format));
+    }
+    if !matches!(format, "json" | "vtt" | "ass") && segments.iter().any(|segment| segment.speaker.is_some()) {
+        warnings.push(format!("Speaker labels are dropped in {}", format));
+    }
+    if !matches!(format, "json" | "csv" | "tsv") && segments.iter().any(|segment| segment.confidence.is_some()) {
+        warnings.push(format!("Confidence scores are dropped in {}", format));
+    }
+
+    let timed = match format {
+        "txt" => false,
+        "md" => settings.transcript_options.markdown_timestamps,
+        _ => true,
+    };
+    if !timed {
+        warnings.push(format!("Cue timing is dropped in {}", format));
+    }
+    let off_grid = |step: u64| segments.iter().any(|segment| segment.start_ms % step != 0 || segment.end_ms % step != 0);
+    match format {
+        "ass" | "lrc" if off_grid(10) => {
+            warnings.push(format!("Times are rounded to hundredths of a second in {}", format));
+        }
+        "ttml" | "ebu-tt-d" => {
+            warnings.push(format!("Times are snapped to {} fps frames in {}", settings.frame_rate, format));
+        }
+        _ => {}
+    }
+    let styled = |segment: &&Segment| !segment.styles.is_empty();
+    let keeps_styles = match format {
+        "srt" | "ttml" | "ebu-tt-d" | "json" => true,
+        // Word-timed payloads are written without styling
+        "vtt" => !settings.vtt_options.word_timestamps || segments.iter().filter(styled).all(|s| s.words.is_empty()),
+        "ass" => !settings.ass_style.karaoke || segments.iter().filter(styled).all(|s| s.words.is_empty()),
+        _ => false,
+    };
+    if !keeps_styles && segments.iter().any(|segment| styled(&segment)) {
+        warnings.push(format!("Italic, bold and underline are dropped in {}", format));
+    }
+    if format == "lrc" && segments.iter().any(|segment| segment.text.trim().contains('\n')) {
+        warnings.push("Line breaks inside cues are joined in lrc".to_string());
+    }
+
+    warnings
+}
+
+/// `srtify convert ...`: converts without opening a window and returns the process exit code.
+/// `settings` are the saved selections, which the command line options adjust.
+pub fn run_cli(args: &[String], settings: ConversionSettings) -> i32 {
+    match parse_cli(args, settings) {
+        Ok(None) => {
+            println!("{}", CLI_USAGE);
+            0
+        }
+        Ok(Some((input, formats, folder, settings))) => match convert_file(&input, &formats, folder.as_deref(), &settings) {
+            Ok(reports) => {
+                for report in reports {
+                    for warning in &report.warnings {
+                        eprintln!("warning: {}", warning);
+                    }
+                    if report.output.is_empty() {
+                        eprintln!("skipped {}: output already exists", report.format);
+                    } else {
+                        println!("{}", report.output);
+                    }
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                1
+            }
+        },
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, CLI_USAGE);
+            2
+        }
+    }
+}
+
+type CliArgs = (PathBuf, Vec<String>, Option<PathBuf>, ConversionSettings);
+
+/// Applies the options to `settings`; returns `None` when help was asked for.
+fn parse_cli(args: &[String], mut settings: ConversionSettings) -> Result<Option<CliArgs>, String> {
+    let mut input = None;
+    let mut formats = None;
+    let mut folder = None;
+    let mut retiming = RetimeOptions::default();
+    let mut anchors = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-t" | "--to" => formats = Some(parse_output_formats(value()?)?),
+            "-o" | "--out" => folder = Some(PathBuf::from(value()?)),
+            "--fps" => {
+                let fps = value()?;
+                settings.frame_rate = fps
+                    .parse()
+                    .ok()
+                    .filter(|fps: &f64| *fps > 0.0)
+                    .ok_or_else(|| format!("Invalid frame rate: {}", fps))?;
+            }
+            "--shift" => retiming.offset = signed_seconds(value()?)?,
+            "--convert-fps" => {
+                let rates = value()?;
+                let (source, target) = rates
+                    .split_once(':')
+                    .and_then(|(source, target)| Some((source.parse().ok()?, target.parse().ok()?)))
+                    .ok_or_else(|| format!("Invalid frame rates {}, expected <from>:<to>", rates))?;
+                retiming.source_fps = Some(source);
+                retiming.target_fps = Some(target);
+            }
+            "--anchor" => {
+                let pair = value()?;
+                let anchor = pair
+                    .split_once('=')
+                    .and_then(|(from, to)| Some((parse_timestamp(from)?, parse_timestamp(to)?)))
+                    .map(|(from, to)| Anchor {
+                        from: from as f64 / 1000.0,
+                        to: to as f64 / 1000.0,
+                    })
+                    .ok_or_else(|| format!("Invalid anchor {}, expected <old>=<new>", pair))?;
+                anchors.push(anchor);
+            }
+            flag if flag.starts_with('-') => return Err(format!("Unknown option: {}", flag)),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            extra => return Err(format!("Unexpected argument: {}", extra)),
+        }
+    }
+
+    let input = input.ok_or("Missing input file")?;
+    let formats = formats.ok_or("Missing --to format")?;
+    retiming.anchors = match anchors.as_slice() {
+        [] => None,
+        &[first, second] => Some([first, second]),
+        _ => return Err("--anchor must be given exactly twice".to_string()),
+    };
+    if retiming != RetimeOptions::default() {
+        settings = settings.with_retiming(retiming)?;
+    }
+    Ok(Some((input, formats, folder, settings)))
+}
+
+/// `1.5`, `-00:00:02,000` or `-0:01.250` as seconds.
+fn signed_seconds(value: &str) -> Result<f64, String> {
+    let (sign, time) = match value.strip_prefix('-') {
+        Some(time) => (-1.0, time),
+        None => (1.0, value.strip_prefix('+').unwrap_or(value)),
+    };
+    parse_timestamp(time)
+        .map(|ms| sign * ms as f64 / 1000.0)
+        .ok_or_else(|| format!("Invalid time: {}", value))
+}
+
+fn is_same_file(output: &Path, input: &Path) -> bool {
+    match (fs::canonicalize(output), fs::canonicalize(input)) {
+        (Ok(output), Ok(input)) => output == input,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Fixed settings, so results never depend on the selections saved on this machine.
+    fn settings() -> ConversionSettings {
+        ConversionSettings {
+            naming_template: DEFAULT_NAMING_TEMPLATE.to_string(),
+            collision_policy: CollisionPolicy::Overwrite,
+            vtt_options: VttOptions::default(),
+            ass_style: AssStyle::default(),
+            transcript_options: TranscriptOptions::default(),
+            lrc_options: LrcOptions::default(),
+            frame_rate: DEFAULT_FRAME_RATE,
+            retiming: None,
+        }
+    }
+
+    fn parse(args: &[&str]) -> Result<Option<CliArgs>, String> {
+        parse_cli(&self::args(args), settings())
+    }
+
+    fn retiming(args: &[&str]) -> Option<RetimeOptions> {
+        let (_, _, _, settings) = parse(args).unwrap().unwrap();
+        settings.retiming
+    }
+
+    #[test]
+    fn parses_input_formats_and_folder() {
+        let (input, formats, folder, settings) =
+            parse(&["talk.srt", "--to", "vtt,ttml", "-o", "out", "--fps", "29.97"]).unwrap().unwrap();
+        assert_eq!(input, PathBuf::from("talk.srt"));
+        assert_eq!(formats, vec!["vtt", "ttml"]);
+        assert_eq!(folder, Some(PathBuf::from("out")));
+        assert_eq!(settings.frame_rate, 29.97);
+        assert_eq!(settings.retiming, None);
+        assert!(parse(&["--help"]).unwrap().is_none());
+    }
+
+    #[test]
+    fn negative_shift_is_a_value_not_a_flag() {
+        assert_eq!(retiming(&["a.srt", "--to", "srt", "--shift", "-0.5"]).map(|r| r.offset), Some(-0.5));
+        assert_eq!(retiming(&["a.srt", "--to", "srt", "--shift", "-00:00:02,000"]).map(|r| r.offset), Some(-2.0));
+        assert_eq!(retiming(&["a.srt", "--to", "srt", "--shift", "+1.25"]).map(|r| r.offset), Some(1.25));
+        assert!(parse(&["a.srt", "--to", "srt", "--shift", "soon"]).is_err());
+    }
+
+    #[test]
+    fn anchors_must_come_in_pairs() {
+        let options = retiming(&[
+            "a.srt", "--to", "srt", "--anchor", "00:01:00=00:01:02", "--anchor", "01:00:00=01:00:05",
+        ]);
+        assert_eq!(
+            options.and_then(|r| r.anchors),
+            Some([Anchor { from: 60.0, to: 62.0 }, Anchor { from: 3600.0, to: 3605.0 }])
+        );
+        assert!(parse(&["a.srt", "--to", "srt", "--anchor", "1=2"]).is_err());
+        let three = ["a.srt", "--to", "srt", "--anchor", "1=2", "--anchor", "3=4", "--anchor", "5=6"];
+        assert!(parse(&three).is_err());
+        assert!(parse(&["a.srt", "--to", "srt", "--anchor", "1-2"]).is_err());
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(parse(&["--to", "srt"]).is_err());
+        assert!(parse(&["a.srt"]).is_err());
+        assert!(parse(&["a.srt", "--to"]).is_err());
+        assert!(parse(&["a.srt", "--to", "srt", "--bogus"]).is_err());
+        assert!(parse(&["a.srt", "b.srt", "--to", "srt"]).is_err());
+        assert!(parse(&["a.srt", "--to", "srt", "--convert-fps", "25"]).is_err());
+    }
+
+    #[test]
+    fn run_cli_converts_and_reports_exit_codes() {
+        let folder = std::env::temp_dir().join(format!("srtify-convert-test-{}", std::process::id()));
+        fs::remove_dir_all(&folder).ok();
+        fs::create_dir_all(&folder).unwrap();
+        let input = folder.join("talk.srt");
+        fs::write(&input, "1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i> <font color=\"red\">you</font>\n").unwrap();
+        let input = input.to_string_lossy().to_string();
+        let run = |args: &[&str]| run_cli(&self::args(args), settings());
+
+        assert_eq!(run(&[&input, "--to", "vtt,txt"]), 0);
+        assert!(fs::read_to_string(folder.join("talk.vtt"))
+            .unwrap()
+            .contains("00:00:01.000 --> 00:00:02.000\n<i>Hello</i> you"));
+        assert!(folder.join("talk.txt").exists());
+
+        let formats = ["srt".to_string(), "txt".to_string()];
+        let reports = convert_file(Path::new(&input), &formats, Some(&folder.join("out")), &settings()).unwrap();
+        let has = |report: &ConversionReport, prefix: &str| report.warnings.iter().any(|warning| warning.starts_with(prefix));
+        assert!(has(&reports[0], "Colour, font") && !has(&reports[0], "Italic"));
+        assert!(has(&reports[1], "Italic, bold and underline are dropped in txt"));
+        assert_eq!(fs::read_to_string(folder.join("out").join("talk.srt")).unwrap(), "1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i> you\n\n");
+
+        assert_eq!(run(&[&folder.join("missing.srt").to_string_lossy(), "--to", "vtt"]), 1);
+        assert_eq!(run(&[&input, "--to", "docx"]), 2);
+        assert_eq!(run(&[&input]), 2);
+        fs::remove_dir_all(&folder).ok();
+    }
+}
@@ -0,0 +1,514 @@
+use futures::StreamExt;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::job::{JobHandle, CANCELLED_MESSAGE};
+
+/// Attempts (the first one included) before a download gives up.
+const MAX_ATTEMPTS: u32 = 6;
+#[cfg(not(test))]
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+#[cfg(test)]
+const INITIAL_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// What a running download reports back to its caller.
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    /// Bytes on disk so far, counting any resumed prefix, and the full size when known.
+    Progress { downloaded: u64, total: Option<u64> },
+    /// The server accepted a Range request; `from` bytes were kept from an earlier attempt.
+    Resumed { from: u64 },
+    /// An attempt failed with a transient error and the next one starts after `delay`.
+    Retrying { attempt: u32, delay: Duration, error: String },
+}
+
+/// A finished transfer: the `.part` file holds `size` bytes hashing to `sha256`.
+#[derive(Debug, Clone)]
+pub struct Downloaded {
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Validators of the response a `.part` file was started from, kept next to it as
+/// `<file>.part.json` so a later run can tell whether the remote file is still the same.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PartValidators {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PartValidators {
+    /// The `If-Range` value: a strong ETag if there is one, the modification date otherwise.
+    fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+enum AttemptError {
+    /// Worth trying again, resuming from whatever reached the disk.
+    Transient(String),
+    Fatal(String),
+}
+
+/// Downloads `url` into `part_path`, resuming an earlier partial transfer with an HTTP
+/// Range request when the server still serves the same file (checked with `If-Range`
+/// against the ETag or Last-Modified date it was started from).
+///
+/// Network errors, 5xx/429 responses and truncated bodies are retried with exponential
+/// backoff; every attempt continues from the bytes already on disk. The `.part` file is
+/// kept on failure and cancellation so the next run can pick it up.
+pub async fn download_resumable(
+    url: &str,
+    part_path: &Path,
+    job: &JobHandle,
+    mut on_event: impl FnMut(DownloadEvent),
+) -> Result<Downloaded, String> {
+    let client = reqwest::Client::new();
+    let mut attempt = 1;
+    loop {
+        match download_attempt(&client, url, part_path, job, &mut on_event).await {
+            Ok(downloaded) => {
+                // Best effort: a stale validator file only costs a restart next time
+                fs::remove_file(validators_path(part_path)).ok();
+                return Ok(downloaded);
+            }
+            Err(AttemptError::Fatal(e)) => return Err(e),
+            Err(AttemptError::Transient(e)) if attempt >= MAX_ATTEMPTS => {
+                return Err(format!("{} (gave up after {} attempts)", e, attempt));
+            }
+            Err(AttemptError::Transient(e)) => {
+                let delay = backoff(attempt);
+                on_event(DownloadEvent::Retrying { attempt, delay, error: e });
+                wait(delay, job).await?;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Path of the validator file that belongs to a `.part` file.
+pub fn validators_path(part_path: &Path) -> PathBuf {
+    let mut path = part_path.as_os_str().to_owned();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+async fn download_attempt(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    job: &JobHandle,
+    on_event: &mut impl FnMut(DownloadEvent),
+) -> Result<Downloaded, AttemptError> {
+    let existing = fs::metadata(part_path).map(|metadata| metadata.len()).unwrap_or(0);
+    let validators = read_validators(part_path).filter(|validators| validators.url == url);
+    // Without a validator there is no way to know the prefix still belongs to the same file
+    let if_range = validators.as_ref().and_then(|validators| validators.if_range().map(str::to_string));
+    let resume_from = if existing > 0 && if_range.is_some() { existing } else { 0 };
+
+    let mut request = client.get(url);
+    if let Some(if_range) = if_range.filter(|_| resume_from > 0) {
+        request = request
+            .header(RANGE, format!("bytes={}-", resume_from))
+            .header(IF_RANGE, if_range);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AttemptError::Transient(format!("Request failed: {}", e)))?;
+
+    let status = response.status();
+    let (offset, total) = match status {
+        StatusCode::PARTIAL_CONTENT if resume_from > 0 => match parse_content_range(&response) {
+            Some((start, total)) if start == resume_from => {
+                (resume_from, total.or_else(|| response.content_length().map(|length| resume_from + length)))
+            }
+            _ => {
+                discard_partial(part_path);
+                return Err(AttemptError::Transient("Server resumed at an unexpected offset".to_string()));
+            }
+        },
+        StatusCode::RANGE_NOT_SATISFIABLE if resume_from > 0 => {
+            discard_partial(part_path);
+            return Err(AttemptError::Transient("Server rejected the resume request".to_string()));
+        }
+        // A full body: the file changed since the `.part` was started, or nothing to resume
+        status if status.is_success() => (0, response.content_length()),
+        status if status.is_server_error()
+            || status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT =>
+        {
+            return Err(AttemptError::Transient(format!("Server responded with {}", status)));
+        }
+        status => {
+            return Err(AttemptError::Fatal(format!("Failed to download model from {}: {}", url, status)));
+        }
+    };
+
+    let io_error = |e: std::io::Error| AttemptError::Fatal(format!("Failed to write {}: {}", part_path.display(), e));
+    let (mut file, mut hasher) = if offset > 0 {
+        on_event(DownloadEvent::Resumed { from: offset });
+        let hasher = hash_prefix(part_path, offset).await.map_err(AttemptError::Fatal)?;
+        (OpenOptions::new().append(true).open(part_path).map_err(io_error)?, hasher)
+    } else {
+        let validators = PartValidators {
+            url: url.to_string(),
+            etag: header(&response, ETAG),
+            last_modified: header(&response, LAST_MODIFIED),
+        };
+        write_validators(part_path, &validators).map_err(AttemptError::Fatal)?;
+        (File::create(part_path).map_err(io_error)?, Sha256::new())
+    };
+
+    let mut downloaded = offset;
+    on_event(DownloadEvent::Progress { downloaded, total });
+
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        if job.is_cancelled() {
+            return Err(AttemptError::Fatal(CANCELLED_MESSAGE.to_string()));
+        }
+        let chunk = chunk.map_err(|e| AttemptError::Transient(format!("Download interrupted: {}", e)))?;
+        file.write_all(&chunk).map_err(io_error)?;
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        on_event(DownloadEvent::Progress { downloaded, total });
+    }
+    file.sync_all().map_err(io_error)?;
+
+    if let Some(total) = total.filter(|&total| downloaded != total) {
+        return Err(AttemptError::Transient(format!(
+            "Download ended after {} of {} bytes",
+            downloaded, total
+        )));
+    }
+
+    Ok(Downloaded {
+        size: downloaded,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Hashes the first `length` bytes already on disk, off the async runtime.
+async fn hash_prefix(path: &Path, length: u64) -> Result<Sha256, String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut reader = file.take(length);
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher)
+    })
+    .await
+    .map_err(|e| format!("Failed to hash partial download: {}", e))?
+}
+
+/// `Content-Range: bytes <start>-<end>/<total>`, where the total may be `*`.
+fn parse_content_range(response: &reqwest::Response) -> Option<(u64, Option<u64>)> {
+    let value = header(response, CONTENT_RANGE)?;
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let start = range.split_once('-')?.0.trim().parse().ok()?;
+    Some((start, total.trim().parse().ok()))
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn read_validators(part_path: &Path) -> Option<PartValidators> {
+    let content = fs::read_to_string(validators_path(part_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write_validators(part_path: &Path, validators: &PartValidators) -> Result<(), String> {
+    let path = validators_path(part_path);
+    let json = serde_json::to_string(validators).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Deletes a `.part` file together with its validators, e.g. once it failed verification.
+pub fn discard_partial(part_path: &Path) {
+    fs::remove_file(part_path).ok();
+    fs::remove_file(validators_path(part_path)).ok();
+}
+
+/// 1 s, 2 s, 4 s, ... capped at [`MAX_BACKOFF`].
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// Sleeps for `delay` but returns early with an error once the job is cancelled.
+async fn wait(delay: Duration, job: &JobHandle) -> Result<(), String> {
+    let step = Duration::from_millis(200);
+    let mut waited = Duration::ZERO;
+    while waited < delay {
+        if job.is_cancelled() {
+            return Err(CANCELLED_MESSAGE.to_string());
+        }
+        tokio::time::sleep(step.min(delay - waited)).await;
+        waited += step;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{HeaderMap, HeaderValue};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use axum::Router;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const ETAG_V1: &str = "\"v1\"";
+
+    fn model_bytes() -> Vec<u8> {
+        (0..100_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn sha256(bytes: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(bytes))
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}/model.bin", address)
+    }
+
+    /// Serves the model with ETag `v1`, honouring `Range` only when `If-Range` still matches.
+    fn ranged(headers: &HeaderMap) -> Response {
+        let body = model_bytes();
+        let etag = [(reqwest::header::ETAG, HeaderValue::from_static(ETAG_V1))];
+        let range = headers.get(RANGE).and_then(|value| value.to_str().ok());
+        let if_range = headers.get(IF_RANGE).and_then(|value| value.to_str().ok());
+        match range.and_then(|range| range.strip_prefix("bytes=")?.strip_suffix('-')?.parse::<usize>().ok()) {
+            Some(start) if if_range == Some(ETAG_V1) => {
+                let content_range = format!("bytes {}-{}/{}", start, body.len() - 1, body.len());
+                (
+                    StatusCode::PARTIAL_CONTENT,
+                    etag,
+                    [(CONTENT_RANGE, content_range)],
+                    body[start..].to_vec(),
+                )
+                    .into_response()
+            }
+            _ => (etag, body).into_response(),
+        }
+    }
+
+    /// A job with its own workspace and a `.part` file inside it, optionally started from
+    /// `prefix` with the given ETag recorded for the model's URL.
+    fn part_file(url: &str, prefix: Option<(&[u8], &str)>) -> (JobHandle, PathBuf) {
+        let job = JobHandle::start(&std::env::temp_dir().join("srtify-download-tests")).unwrap();
+        let part_path = job.workspace().join("model.bin.part");
+        if let Some((bytes, etag)) = prefix {
+            fs::write(&part_path, bytes).unwrap();
+            let validators = PartValidators { url: url.to_string(), etag: Some(etag.to_string()), last_modified: None };
+            write_validators(&part_path, &validators).unwrap();
+        }
+        (job, part_path)
+    }
+
+    async fn download(url: &str, part_path: &Path, job: &JobHandle) -> (Result<Downloaded, String>, Vec<DownloadEvent>) {
+        let events = Mutex::new(Vec::new());
+        let result = download_resumable(url, part_path, job, |event| events.lock().unwrap().push(event)).await;
+        (result, events.into_inner().unwrap())
+    }
+
+    fn progress(events: &[DownloadEvent]) -> Vec<(u64, Option<u64>)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                DownloadEvent::Progress { downloaded, total } => Some((*downloaded, *total)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn resumed_from(events: &[DownloadEvent]) -> Vec<u64> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                DownloadEvent::Resumed { from } => Some(*from),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn retries(events: &[DownloadEvent]) -> Vec<(u32, Duration, String)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                DownloadEvent::Retrying { attempt, delay, error } => Some((*attempt, *delay, error.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The whole model reached the disk and the validators are gone.
+    fn assert_complete(result: Result<Downloaded, String>, part_path: &Path) {
+        let body = model_bytes();
+        let downloaded = result.unwrap();
+        assert_eq!(downloaded.size, body.len() as u64);
+        assert_eq!(downloaded.sha256, sha256(&body));
+        assert_eq!(fs::read(part_path).unwrap(), body);
+        assert!(!validators_path(part_path).exists());
+    }
+
+    #[tokio::test]
+    async fn resumes_with_a_matching_if_range() {
+        let url = serve(Router::new().route("/model.bin", get(|headers: HeaderMap| async move { ranged(&headers) }))).await;
+        let body = model_bytes();
+        let (job, part_path) = part_file(&url, Some((&body[..30_000], ETAG_V1)));
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert_complete(result, &part_path);
+        assert_eq!(resumed_from(&events), vec![30_000]);
+        assert_eq!(progress(&events).first(), Some(&(30_000, Some(body.len() as u64))));
+        job.finish();
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_etag_changed() {
+        let url = serve(Router::new().route("/model.bin", get(|headers: HeaderMap| async move { ranged(&headers) }))).await;
+        let (job, part_path) = part_file(&url, Some((b"bytes of an older upload", "\"v0\"")));
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert_complete(result, &part_path);
+        assert!(resumed_from(&events).is_empty());
+        assert_eq!(progress(&events).first(), Some(&(0, Some(model_bytes().len() as u64))));
+        job.finish();
+    }
+
+    #[tokio::test]
+    async fn a_resume_at_the_wrong_offset_is_discarded() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counted = hits.clone();
+        let handler = move |headers: HeaderMap| async move {
+            if counted.fetch_add(1, Ordering::SeqCst) > 0 {
+                return ranged(&headers);
+            }
+            // Claims to resume but starts from the beginning of the file
+            let body = model_bytes();
+            let content_range = format!("bytes 0-{}/{}", body.len() - 1, body.len());
+            (StatusCode::PARTIAL_CONTENT, [(CONTENT_RANGE, content_range)], body).into_response()
+        };
+        let url = serve(Router::new().route("/model.bin", get(handler))).await;
+        let body = model_bytes();
+        let (job, part_path) = part_file(&url, Some((&body[..1000], ETAG_V1)));
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert_complete(result, &part_path);
+        let retries = retries(&events);
+        assert_eq!(retries.len(), 1);
+        assert!(retries[0].2.contains("unexpected offset"), "{}", retries[0].2);
+        assert!(resumed_from(&events).is_empty());
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        job.finish();
+    }
+
+    #[tokio::test]
+    async fn busy_servers_are_retried_with_backoff() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counted = hits.clone();
+        let handler = move |headers: HeaderMap| async move {
+            match counted.fetch_add(1, Ordering::SeqCst) {
+                0 => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+                1 => StatusCode::TOO_MANY_REQUESTS.into_response(),
+                _ => ranged(&headers),
+            }
+        };
+        let url = serve(Router::new().route("/model.bin", get(handler))).await;
+        let (job, part_path) = part_file(&url, None);
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert_complete(result, &part_path);
+        let retries = retries(&events);
+        assert_eq!(
+            retries.iter().map(|(attempt, delay, _)| (*attempt, *delay)).collect::<Vec<_>>(),
+            vec![(1, INITIAL_BACKOFF), (2, INITIAL_BACKOFF * 2)]
+        );
+        assert!(retries[0].2.contains("503") && retries[1].2.contains("429"));
+        job.finish();
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        let url = serve(Router::new().route("/model.bin", get(|| async { StatusCode::NOT_FOUND }))).await;
+        let (job, part_path) = part_file(&url, None);
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert!(result.unwrap_err().contains("404"));
+        assert!(retries(&events).is_empty());
+        job.finish();
+    }
+
+    #[tokio::test]
+    async fn a_truncated_body_is_resumed_and_progress_keeps_counting() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counted = hits.clone();
+        let handler = move |headers: HeaderMap| async move {
+            if counted.fetch_add(1, Ordering::SeqCst) > 0 {
+                return ranged(&headers);
+            }
+            // Announces the whole model, then the connection drops after 40 000 bytes
+            let body = model_bytes();
+            let dropped = async {
+                // Give the first chunk time to reach the client before the connection goes
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "dropped"))
+            };
+            let chunks = futures::stream::iter([Ok(body[..40_000].to_vec())]).chain(futures::stream::once(dropped));
+            let mut response = Body::from_stream(chunks).into_response();
+            response.headers_mut().insert(reqwest::header::ETAG, HeaderValue::from_static(ETAG_V1));
+            response.headers_mut().insert(reqwest::header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+            response
+        };
+        let url = serve(Router::new().route("/model.bin", get(handler))).await;
+        let (job, part_path) = part_file(&url, None);
+
+        let (result, events) = download(&url, &part_path, &job).await;
+        assert_complete(result, &part_path);
+        assert_eq!(retries(&events).len(), 1);
+        assert_eq!(resumed_from(&events), vec![40_000]);
+
+        let progress = progress(&events);
+        let total = model_bytes().len() as u64;
+        assert_eq!(progress.first(), Some(&(0, Some(total))));
+        assert_eq!(progress.last(), Some(&(total, Some(total))));
+        assert!(progress.contains(&(40_000, Some(total))));
+        // Counts are cumulative over both attempts, never restarting from the resumed chunk
+        assert!(progress.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        job.finish();
+    }
+}
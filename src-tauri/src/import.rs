@@ -0,0 +1,730 @@
+use std::fs;
+use std::path::Path;
+
+use crate::timing::sanitize_cues;
+use crate::transcript::{Segment, StyleSpan, TextStyle, Transcript, Word};
+
+/// Subtitle formats that can be read back into a [`Transcript`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Srt,
+    Vtt,
+    Ass,
+    Json,
+}
+
+impl ImportFormat {
+    /// Picks the parser from the file extension, falling back to sniffing the content.
+    fn detect(path: &Path, content: &str) -> Option<ImportFormat> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("srt") => return Some(ImportFormat::Srt),
+            Some("vtt") => return Some(ImportFormat::Vtt),
+            Some("ass" | "ssa") => return Some(ImportFormat::Ass),
+            Some("json") => return Some(ImportFormat::Json),
+            _ => {}
+        }
+
+        let head = content.trim_start();
+        if head.starts_with("WEBVTT") {
+            Some(ImportFormat::Vtt)
+        } else if head.starts_with("[Script Info]") {
+            Some(ImportFormat::Ass)
+        } else if head.starts_with('{') {
+            Some(ImportFormat::Json)
+        } else if head.contains("-->") {
+            Some(ImportFormat::Srt)
+        } else {
+            None
+        }
+    }
+}
+
+#[tauri::command]
+pub fn import_subtitle(path: String) -> Result<Transcript, String> {
+    import_file(Path::new(&path))
+}
+
+/// Reads an SRT, WebVTT, ASS/SSA or SRTify JSON file into a transcript with repaired cue timing.
+pub fn import_file(path: &Path) -> Result<Transcript, String> {
+    import_file_with_warnings(path).map(|(transcript, _)| transcript)
+}
+
+/// Like [`import_file`], also listing what the file carried that a transcript cannot hold.
+pub fn import_file_with_warnings(path: &Path) -> Result<(Transcript, Vec<String>), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let content = normalize(&String::from_utf8_lossy(&bytes));
+    let format = ImportFormat::detect(path, &content)
+        .ok_or_else(|| format!("Unrecognised subtitle format: {}", path.display()))?;
+
+    let mut transcript = match format {
+        ImportFormat::Srt => Transcript::new(parse_srt(&content)),
+        ImportFormat::Vtt => Transcript::new(parse_vtt(&content)),
+        ImportFormat::Ass => Transcript::new(parse_ass(&content)?),
+        ImportFormat::Json => parse_json(&content)?,
+    };
+    if transcript.segments.is_empty() {
+        return Err(format!("No cues found in {}", path.display()));
+    }
+
+    sanitize_cues(&mut transcript.segments);
+    if transcript.source.is_none() {
+        transcript.source = path.file_name().map(|name| name.to_string_lossy().to_string());
+    }
+
+    let mut warnings = Vec::new();
+    match format {
+        ImportFormat::Ass => warnings.push(
+            "ASS styles, positioning and override tags other than italic, bold and underline are not carried over"
+                .to_string(),
+        ),
+        ImportFormat::Srt | ImportFormat::Vtt if has_dropped_styling(&content) => {
+            warnings.push("Colour, font, class and position tags are not carried over".to_string());
+        }
+        _ => {}
+    }
+    Ok((transcript, warnings))
+}
+
+/// Parses SRT, tolerating missing blank lines between cues, missing or bogus
+/// index lines and `.` instead of `,` before the milliseconds.
+///
+/// A cue's text ends at the first blank line. Without one, a single numeric last line
+/// right before the next timing line is taken to be the next cue's index.
+pub fn parse_srt(content: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current: Option<(u64, u64, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        if let Some((start, end)) = parse_timing_line(line) {
+            if let Some(cue) = current.take() {
+                segments.extend(finish_cue(cue, None, true));
+            }
+            current = Some((start, end, Vec::new()));
+        } else if let Some((_, _, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some(cue) = current.take() {
+        segments.extend(finish_cue(cue, None, false));
+    }
+    segments
+}
+
+/// Parses WebVTT, skipping the header, `NOTE`, `STYLE` and `REGION` blocks and cue
+/// identifiers. `<v Speaker>` tags become the segment speaker and `<i>`, `<b>` and `<u>`
+/// its styling; other markup is dropped.
+pub fn parse_vtt(content: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current: Option<(u64, u64, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        if let Some((start, end)) = parse_timing_line(line) {
+            if let Some(cue) = current.take() {
+                segments.extend(finish_vtt_cue(cue, true));
+            }
+            current = Some((start, end, Vec::new()));
+        } else if line.trim().is_empty() {
+            if let Some(cue) = current.take() {
+                segments.extend(finish_vtt_cue(cue, false));
+            }
+        } else if let Some((_, _, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+        // Anything outside a cue is the header, a cue identifier or a NOTE/STYLE/REGION block
+    }
+    if let Some(cue) = current.take() {
+        segments.extend(finish_vtt_cue(cue, false));
+    }
+    segments
+}
+
+/// Parses the `[Events]` section of an ASS/SSA script using its `Format:` line.
+///
+/// `\k` karaoke tags are turned back into word timings and `\i1`, `\b1` and `\u1` into
+/// styling; other override blocks are removed.
+pub fn parse_ass(content: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut in_events = false;
+    let mut columns: Vec<String> = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_events = line.eq_ignore_ascii_case("[Events]");
+            continue;
+        }
+        if !in_events {
+            continue;
+        }
+
+        if let Some(format) = line.strip_prefix("Format:") {
+            columns = format.split(',').map(|c| c.trim().to_lowercase()).collect();
+            continue;
+        }
+        let Some(fields) = line.strip_prefix("Dialogue:") else {
+            continue;
+        };
+        if columns.is_empty() {
+            return Err("ASS [Events] section has no Format line".to_string());
+        }
+
+        // Text is always the last column and may itself contain commas
+        let values: Vec<&str> = fields.splitn(columns.len(), ',').map(str::trim).collect();
+        let column = |name: &str| columns.iter().position(|c| c == name).and_then(|i| values.get(i).copied());
+
+        let (Some(start), Some(end), Some(text)) = (
+            column("start").and_then(parse_timestamp),
+            column("end").and_then(parse_timestamp),
+            column("text"),
+        ) else {
+            continue;
+        };
+
+        let (plain, words, styles) = ass_text(text, start);
+        if plain.trim().is_empty() {
+            continue;
+        }
+        segments.push(Segment {
+            start_ms: start,
+            end_ms: end,
+            text: plain,
+            speaker: column("name").filter(|name| !name.is_empty()).map(str::to_string),
+            styles,
+            words,
+            ..Default::default()
+        });
+    }
+
+    Ok(segments)
+}
+
+/// Reads a `.words.json` transcript; files written before the schema was versioned
+/// (`start`/`end` in seconds, words as `{word, start, end}`) are upgraded on the fly.
+pub fn parse_json(content: &str) -> Result<Transcript, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).map_err(|e| format!("Invalid transcript JSON: {}", e))?;
+    if value.get("schema_version").is_some() {
+        return Transcript::from_json(content);
+    }
+
+    let seconds = |v: &serde_json::Value| v.as_f64().map(crate::timing::sec_to_ms).unwrap_or_default();
+    let segments = value["segments"]
+        .as_array()
+        .ok_or("Transcript JSON has no segments array")?
+        .iter()
+        .map(|segment| Segment {
+            start_ms: seconds(&segment["start"]),
+            end_ms: seconds(&segment["end"]),
+            text: segment["text"].as_str().unwrap_or_default().trim().to_string(),
+            confidence: segment["confidence"].as_f64().map(|c| c as f32),
+            words: segment["words"]
+                .as_array()
+                .map(|words| {
+                    words
+                        .iter()
+                        .map(|word| Word {
+                            text: word["word"].as_str().unwrap_or_default().trim().to_string(),
+                            start_ms: seconds(&word["start"]),
+                            end_ms: seconds(&word["end"]),
+                            ..Default::default()
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ..Default::default()
+        })
+        .collect();
+
+    Ok(Transcript {
+        language: value["language"].as_str().map(str::to_string),
+        language_probability: value["language_probability"].as_f64().map(|p| p as f32),
+        task: value["task"].as_str().map(str::to_string),
+        ..Transcript::new(segments)
+    })
+}
+
+/// Strips a UTF-8 BOM and normalizes `\r\n` / `\r` line endings.
+fn normalize(content: &str) -> String {
+    content.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Parses `start --> end`, ignoring any WebVTT cue settings or SRT coordinates after it.
+fn parse_timing_line(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Accepts `HH:MM:SS,mmm`, `HH:MM:SS.mmm`, `MM:SS.mmm` and ASS's `H:MM:SS.cc`.
+///
+/// The fraction is read as a decimal, so `.5`, `.50` and `.500` all mean 500 ms.
+pub fn parse_timestamp(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (clock, fraction) = match value.rfind([',', '.']) {
+        Some(i) => (&value[..i], &value[i + 1..]),
+        None => (value, ""),
+    };
+
+    let parts: Vec<u64> = clock.split(':').map(|p| p.trim().parse().ok()).collect::<Option<_>>()?;
+    let seconds = match parts.as_slice() {
+        [h, m, s] => h * 3600 + m * 60 + s,
+        [m, s] => m * 60 + s,
+        [s] => *s,
+        _ => return None,
+    };
+
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let millis = format!("{:0<3}", &fraction[..fraction.len().min(3)]).parse::<u64>().ok()?;
+    Some(seconds * 1000 + millis)
+}
+
+/// Builds a segment from the lines collected after a timing line. `next_cue_follows` is
+/// set when the cue was ended by the next timing line rather than a blank line or the end
+/// of the file.
+fn finish_cue(
+    (start, end, lines): (u64, u64, Vec<&str>),
+    speaker: Option<String>,
+    next_cue_follows: bool,
+) -> Option<Segment> {
+    let mut lines: Vec<&str> = lines.into_iter().map(str::trim).collect();
+    if let Some(blank) = lines.iter().position(|line| line.is_empty()) {
+        // A blank line ends the text, whatever follows is the next cue's index
+        lines.truncate(blank);
+    } else if next_cue_follows
+        && lines.len() > 1
+        && lines.last().is_some_and(|line| line.chars().all(|c| c.is_ascii_digit()))
+    {
+        // Without a blank separator the next cue's index ends up as our last line
+        lines.pop();
+    }
+    let (text, styles) = parse_markup(&lines);
+
+    (!text.is_empty()).then(|| Segment {
+        start_ms: start,
+        end_ms: end,
+        text,
+        speaker,
+        styles,
+        ..Default::default()
+    })
+}
+
+fn finish_vtt_cue((start, end, lines): (u64, u64, Vec<&str>), next_cue_follows: bool) -> Option<Segment> {
+    // `<v Speaker>` or `<v.class Speaker>`
+    let speaker = lines.iter().find_map(|line| {
+        let tag = &line[line.find("<v")? + 2..];
+        let annotation = &tag[..tag.find('>')?];
+        let name = match annotation.strip_prefix('.') {
+            Some(classes) => classes.split_once(char::is_whitespace).map_or("", |(_, name)| name),
+            None => annotation,
+        };
+        (!name.trim().is_empty()).then(|| name.trim().to_string())
+    });
+    finish_cue((start, end, lines), speaker, next_cue_follows)
+}
+
+/// Tags the importers understand. Anything else in angle brackets is cue text.
+const MARKUP_TAGS: &[&str] = &["i", "b", "u", "s", "font", "c", "v", "lang", "ruby", "rt"];
+
+/// A piece of a cue line: text, or a recognised tag such as `<i>`, `</b>`,
+/// `<font color="red">`, `<v Ann>`, a WebVTT word timestamp or a `{\an8}` override.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Markup<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+/// Splits a line into text and tags. A `<` or `{` that does not open a recognised,
+/// closed tag (`a < b`, `{laughs}`) stays text.
+fn split_markup(line: &str) -> Vec<Markup<'_>> {
+    let mut pieces = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        match markup_tag_len(rest) {
+            Some(length) => {
+                if text_start < i {
+                    pieces.push(Markup::Text(&line[text_start..i]));
+                }
+                pieces.push(Markup::Tag(&rest[..length]));
+                i += length;
+                text_start = i;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if text_start < line.len() {
+        pieces.push(Markup::Text(&line[text_start..]));
+    }
+    pieces
+}
+
+/// Length of the tag `rest` starts with, when it is one of [`MARKUP_TAGS`] (opening or
+/// closing), a WebVTT `<00:01.500>` timestamp or an ASS `{\...}` override block.
+fn markup_tag_len(rest: &str) -> Option<usize> {
+    if rest.starts_with("{\\") {
+        let close = rest.find('}')?;
+        return (!rest[1..close].contains('{')).then_some(close + 1);
+    }
+
+    let inner = &rest.strip_prefix('<')?[..rest.find('>')? - 1];
+    if inner.contains('<') {
+        return None;
+    }
+    if let Some(name) = inner.strip_prefix('/') {
+        return MARKUP_TAGS.contains(&name.trim().to_lowercase().as_str()).then_some(inner.len() + 2);
+    }
+    if inner.contains(':') && parse_timestamp(inner).is_some() {
+        return Some(inner.len() + 2);
+    }
+
+    // `<i>` and `<c.yellow>` take classes only, so `a <b and c> d` is not bold
+    let split = inner.find([' ', '\t', '.']).unwrap_or(inner.len());
+    let (name, annotation) = (inner[..split].to_lowercase(), &inner[split..]);
+    let classes_only = annotation.is_empty() || (annotation.starts_with('.') && !annotation.contains(char::is_whitespace));
+    let known = match name.as_str() {
+        "font" => annotation.is_empty() || annotation.starts_with(char::is_whitespace),
+        "v" | "lang" => !annotation.is_empty(),
+        name => MARKUP_TAGS.contains(&name) && classes_only,
+    };
+    known.then_some(inner.len() + 2)
+}
+
+/// Whether cue text carries markup that is dropped on import: tags such as `<font>` or
+/// `<c.class>` and `{\\an8}`-style overrides. Italic, bold and underline are kept, and
+/// WebVTT voice tags, bare `<c>` spans and word timestamps are not styling.
+fn has_dropped_styling(content: &str) -> bool {
+    content.lines().filter(|line| parse_timing_line(line).is_none()).any(|line| {
+        split_markup(line).into_iter().any(|piece| match piece {
+            Markup::Tag(tag) if tag.starts_with('{') => {
+                let block = &tag[1..tag.len() - 1];
+                block.split('\\').filter(|code| !code.is_empty()).any(|code| ass_style_switch(code).is_none())
+            }
+            Markup::Tag(tag) => {
+                let tag = tag.trim_start_matches('<').trim_start_matches('/').trim_end_matches('>');
+                let name = tag.split([' ', '\t', '.']).next().unwrap_or_default().to_lowercase();
+                let kept = name == "v" || tag == "c" || (text_style(&name).is_some() && !tag.contains('.'));
+                !(kept || name.starts_with(|c: char| c.is_ascii_digit()))
+            }
+            Markup::Text(_) => false,
+        })
+    })
+}
+
+/// Joins cue lines into plain text, trimming each line and leaving out empty ones.
+/// `<i>`, `<b>`, `<u>` and `{\\i1}`-style switches become style ranges, other recognised
+/// tags (see [`split_markup`]) are removed and the common HTML entities decoded.
+fn parse_markup(lines: &[&str]) -> (String, Vec<StyleSpan>) {
+    let mut text = String::new();
+    let mut styles = StyleTracker::default();
+    for line in lines {
+        let mut plain = String::new();
+        let mut switches = Vec::new();
+        for piece in split_markup(line) {
+            match piece {
+                Markup::Text(piece) => plain.push_str(&decode_entities(piece)),
+                Markup::Tag(tag) => {
+                    switches.extend(style_switches(tag).into_iter().map(|(style, on)| (style, on, plain.len())));
+                }
+            }
+        }
+
+        let lead = plain.len() - plain.trim_start().len();
+        let trimmed = plain.trim();
+        if !trimmed.is_empty() && !text.is_empty() {
+            text.push('\n');
+        }
+        let base = text.len();
+        text.push_str(trimmed);
+        for (style, on, at) in switches {
+            styles.switch(style, on, base + at.saturating_sub(lead).min(trimmed.len()));
+        }
+    }
+    let end = text.len();
+    (text, styles.finish(end))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Turns style switches at byte offsets of the text being built into ranges.
+#[derive(Default)]
+struct StyleTracker {
+    open: Vec<(TextStyle, usize)>,
+    spans: Vec<StyleSpan>,
+}
+
+impl StyleTracker {
+    fn switch(&mut self, style: TextStyle, on: bool, at: usize) {
+        match (on, self.open.iter().position(|(open, _)| *open == style)) {
+            (true, None) => self.open.push((style, at)),
+            (false, Some(index)) => {
+                let (_, start) = self.open.remove(index);
+                if start < at {
+                    self.spans.push(StyleSpan { start, end: at, style });
+                }
+            }
+            // Switching on what is on, or off what is off, changes nothing
+            _ => {}
+        }
+    }
+
+    /// Closes whatever is still open at `end`, the end of the text.
+    fn finish(mut self, end: usize) -> Vec<StyleSpan> {
+        for (style, _) in self.open.clone() {
+            self.switch(style, false, end);
+        }
+        self.spans.sort_by_key(|span| (span.start, std::cmp::Reverse(span.end)));
+        self.spans
+    }
+}
+
+/// The style switches a recognised tag stands for: `<i>`/`</i>`, `<b>` and `<u>` (with or
+/// without WebVTT classes), or the `\\i`, `\\b`, `\\u` and `\\r` codes of an ASS override block.
+fn style_switches(tag: &str) -> Vec<(TextStyle, bool)> {
+    if let Some(block) = tag.strip_prefix('{').and_then(|tag| tag.strip_suffix('}')) {
+        return block.split('\\').filter_map(ass_style_switch).flatten().collect();
+    }
+    let inner = tag.trim_start_matches('<').trim_end_matches('>');
+    let (on, name) = match inner.strip_prefix('/') {
+        Some(name) => (false, name),
+        None => (true, inner),
+    };
+    let name = name.split('.').next().unwrap_or_default().trim().to_lowercase();
+    text_style(&name).map(|style| vec![(style, on)]).unwrap_or_default()
+}
+
+/// `i1`/`i0`, `b1` (or a weight such as `b700`), `u1`, a bare `i` that resets to the style's
+/// default, or `r` that resets everything. `None` for codes that are not about these styles.
+fn ass_style_switch(code: &str) -> Option<Vec<(TextStyle, bool)>> {
+    if code.starts_with('r') {
+        return Some([TextStyle::Italic, TextStyle::Bold, TextStyle::Underline].map(|style| (style, false)).to_vec());
+    }
+    let mut chars = code.chars();
+    let style = text_style(&chars.next()?.to_string())?;
+    let value = chars.as_str().trim();
+    if !value.chars().all(|c| c.is_ascii_digit()) {
+        // `\\bord2`, `\\be1`, `\\blur3`
+        return None;
+    }
+    Some(vec![(style, value.parse::<u32>().is_ok_and(|value| value > 0))])
+}
+
+fn text_style(name: &str) -> Option<TextStyle> {
+    match name {
+        "i" => Some(TextStyle::Italic),
+        "b" => Some(TextStyle::Bold),
+        "u" => Some(TextStyle::Underline),
+        _ => None,
+    }
+}
+
+/// Splits ASS dialogue text into plain text, its italic, bold and underline ranges and,
+/// when it carries `\k` tags, word timings.
+fn ass_text(text: &str, start_ms: u64) -> (String, Vec<Word>, Vec<StyleSpan>) {
+    let text = text.replace("\\N", "\n").replace("\\n", "\n").replace("\\h", " ");
+    let mut plain = String::new();
+    let mut words = Vec::new();
+    let mut styles = StyleTracker::default();
+    let mut cursor = start_ms;
+    let mut rest = text.as_str();
+
+    while !rest.is_empty() {
+        let Some(open) = rest.find('{') else {
+            plain.push_str(rest);
+            break;
+        };
+        plain.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+            plain.push_str(&rest[open..]);
+            break;
+        };
+
+        let block = &rest[open + 1..close];
+        for (style, on) in style_switches(&rest[open..=close]) {
+            styles.switch(style, on, plain.len());
+        }
+        rest = &rest[close + 1..];
+        let Some(duration) = karaoke_duration(block) else {
+            continue;
+        };
+
+        // The syllable runs until the next override block
+        let syllable = &rest[..rest.find('{').unwrap_or(rest.len())];
+        let syllable_end = cursor + duration * 10;
+        if !syllable.trim().is_empty() {
+            words.push(Word {
+                text: syllable.trim().to_string(),
+                start_ms: cursor,
+                end_ms: syllable_end,
+                ..Default::default()
+            });
+        }
+        cursor = syllable_end;
+        plain.push_str(syllable);
+        rest = &rest[syllable.len()..];
+    }
+
+    let lead = plain.len() - plain.trim_start().len();
+    let trimmed = plain.trim();
+    let styles = styles
+        .finish(plain.len())
+        .into_iter()
+        .map(|span| StyleSpan {
+            start: span.start.saturating_sub(lead).min(trimmed.len()),
+            end: span.end.saturating_sub(lead).min(trimmed.len()),
+            style: span.style,
+        })
+        .filter(|span| span.start < span.end)
+        .collect();
+    (trimmed.to_string(), words, styles)
+}
+
+/// Reads the duration in centiseconds from a `\k`, `\K`, `\kf` or `\ko` override block.
+fn karaoke_duration(block: &str) -> Option<u64> {
+    block.split('\\').find_map(|tag| {
+        let digits = tag
+            .strip_prefix("kf")
+            .or_else(|| tag.strip_prefix("ko"))
+            .or_else(|| tag.strip_prefix('k'))
+            .or_else(|| tag.strip_prefix('K'))?;
+        digits.trim().parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cues(segments: &[Segment]) -> Vec<(u64, u64, &str)> {
+        segments.iter().map(|s| (s.start_ms, s.end_ms, s.text.as_str())).collect()
+    }
+
+    #[test]
+    fn srt_with_bom_and_crlf() {
+        let content = normalize(
+            "\u{feff}1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\nthere\r\n\r\n\
+             2\r\n00:00:03,000 --> 00:00:04,000\r\nBye\r\n",
+        );
+        assert_eq!(cues(&parse_srt(&content)), vec![(1000, 2000, "Hello\nthere"), (3000, 4000, "Bye")]);
+    }
+
+    #[test]
+    fn srt_without_blank_lines_drops_only_the_next_index() {
+        let content = "1\n00:00:01,000 --> 00:00:02,000\nThe year was\n1984\n\
+                       2\n00:00:03,000 --> 00:00:04,000\n42\n\
+                       3\n00:00:05,000 --> 00:00:06,000\nEnd\n";
+        assert_eq!(
+            cues(&parse_srt(content)),
+            vec![(1000, 2000, "The year was\n1984"), (3000, 4000, "42"), (5000, 6000, "End")]
+        );
+    }
+
+    #[test]
+    fn numeric_text_survives_a_blank_line() {
+        let content = "1\n00:00:01,000 --> 00:00:02,000\nThe year was\n1984\n\n\
+                       2\n00:00:03,000 --> 00:00:04,000\n42\n\n\
+                       3\n00:00:05,000 --> 00:00:06,000\n7\n";
+        assert_eq!(
+            cues(&parse_srt(content)),
+            vec![(1000, 2000, "The year was\n1984"), (3000, 4000, "42"), (5000, 6000, "7")]
+        );
+    }
+
+    #[test]
+    fn mixed_timestamp_separators() {
+        let content = "00:00:01.500 --> 00:00:02,250\nDot\n\n00:00:03,5 --> 00:01:04.25\nShort fractions\n";
+        assert_eq!(cues(&parse_srt(content)), vec![(1500, 2250, "Dot"), (3500, 64_250, "Short fractions")]);
+        assert_eq!(parse_timestamp("0:00:01.50"), Some(1500));
+        assert_eq!(parse_timestamp("01:02.003"), Some(62_003));
+        assert_eq!(parse_timestamp("00:00:01,x"), None);
+    }
+
+    #[test]
+    fn vtt_skips_header_notes_and_identifiers() {
+        let content = "WEBVTT\n\nNOTE written by hand\n\n\
+                       intro\n00:01.000 --> 00:02.000 align:start\n<v Ann>Hi &amp; welcome\n\n\
+                       00:03.000 --> 00:04.000\n<i>Bye</i>\n";
+        let segments = parse_vtt(content);
+        assert_eq!(cues(&segments), vec![(1000, 2000, "Hi & welcome"), (3000, 4000, "Bye")]);
+        assert_eq!(segments[0].speaker.as_deref(), Some("Ann"));
+    }
+
+    #[test]
+    fn styling_is_detected_but_voices_and_word_timing_are_not() {
+        assert!(has_dropped_styling("<font color=\"#ff0000\">Red</font>"));
+        assert!(has_dropped_styling("{\\an8}Top"));
+        assert!(has_dropped_styling("{\\i1\\c&H0000FF&}Red italic"));
+        assert!(has_dropped_styling("<c.yellow>Loud</c>"));
+        assert!(!has_dropped_styling("1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i> <B>you</B> {\\u1}there{\\u0}\n"));
+        assert!(!has_dropped_styling("WEBVTT\n\n00:01.000 --> 00:02.000\n<v Ann><c>Hi</c> <00:01.500><c>there</c>\n"));
+        assert!(!has_dropped_styling("Plain text, 1 < 2 and {braces}"));
+        assert!(!has_dropped_styling("<3 you, see <https://example.com>"));
+    }
+
+    fn plain(line: &str) -> String {
+        parse_markup(&[line]).0
+    }
+
+    fn spans(segment: &Segment) -> Vec<(&str, TextStyle)> {
+        segment.styles.iter().map(|span| (&segment.text[span.start..span.end], span.style)).collect()
+    }
+
+    #[test]
+    fn only_recognised_tags_are_stripped() {
+        assert_eq!(plain("if a < b then {laughs}"), "if a < b then {laughs}");
+        assert_eq!(plain("<i>so</i> a<b and b>c"), "so a<b and b>c");
+        assert_eq!(plain("<font color=\"#ff0000\">Red</FONT> <B>bold</B>"), "Red bold");
+        assert_eq!(plain("{\\an8}Top {note} {\\i1}it{\\i0}"), "Top {note} it");
+        assert_eq!(plain("<c.yellow>Loud</c> <00:01.500>now <v Ann>hi</v>"), "Loud now hi");
+        assert_eq!(plain("unclosed <i and {\\an8 too"), "unclosed <i and {\\an8 too");
+        assert_eq!(plain("<html> &lt;i&gt; stays"), "<html> <i> stays");
+    }
+
+    #[test]
+    fn srt_styling_becomes_style_ranges() {
+        let content = "1\n00:00:01,000 --> 00:00:02,000\n <i>Hello\nthere</i> <b><u>you</u> two</b>\n\n\
+                       2\n00:00:03,000 --> 00:00:04,000\n{\\i1}Sure{\\i0} &amp; <i.loud>unclosed\n";
+        let segments = parse_srt(content);
+        assert_eq!(cues(&segments), vec![(1000, 2000, "Hello\nthere you two"), (3000, 4000, "Sure & unclosed")]);
+        assert_eq!(
+            spans(&segments[0]),
+            vec![("Hello\nthere", TextStyle::Italic), ("you two", TextStyle::Bold), ("you", TextStyle::Underline)]
+        );
+        assert_eq!(spans(&segments[1]), vec![("Sure", TextStyle::Italic), ("unclosed", TextStyle::Italic)]);
+    }
+
+    #[test]
+    fn ass_style_switches_become_style_ranges() {
+        let content = "[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                       Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{\\an8\\i1} Top{\\i0} {\\b700\\bord2}bold{\\r} plain\n";
+        let segments = parse_ass(content).unwrap();
+        assert_eq!(cues(&segments), vec![(1000, 3000, "Top bold plain")]);
+        assert_eq!(spans(&segments[0]), vec![("Top", TextStyle::Italic), ("bold", TextStyle::Bold)]);
+    }
+
+    #[test]
+    fn literal_brackets_survive_an_srt_round_trip() {
+        let content = "1\n00:00:01,000 --> 00:00:02,000\nx < y {sighs}\n";
+        assert_eq!(cues(&parse_srt(content)), vec![(1000, 2000, "x < y {sighs}")]);
+    }
+
+    #[test]
+    fn ass_karaoke_becomes_word_timing() {
+        let content = "[Script Info]\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+                       Dialogue: 0,0:00:01.00,0:00:03.00,Default,Bob,0,0,0,,{\\k50}Hello, {\\k100}world\n";
+        let segments = parse_ass(content).unwrap();
+        assert_eq!(cues(&segments), vec![(1000, 3000, "Hello, world")]);
+        assert_eq!(segments[0].speaker.as_deref(), Some("Bob"));
+        let words: Vec<_> = segments[0].words.iter().map(|w| (w.text.as_str(), w.start_ms, w.end_ms)).collect();
+        assert_eq!(words, vec![("Hello,", 1000, 1500), ("world", 1500, 2500)]);
+    }
+}
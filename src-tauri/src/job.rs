@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const CANCELLED_MESSAGE: &str = "Transcription cancelled";
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+static RUNNING_JOBS: Mutex<Vec<JobHandle>> = Mutex::new(Vec::new());
+
+/// Shared handle for a single transcription run.
+///
+/// Every long running step (ffmpeg extraction, model download, whisper inference)
+/// polls the handle so `stop_transcription` can interrupt it from another command.
+/// Each job also owns a private workspace directory for its intermediate files, so
+/// concurrent jobs and app instances never overwrite each other's audio.
+#[derive(Clone)]
+pub struct JobHandle {
+    inner: Arc<JobInner>,
+}
+
+struct JobInner {
+    id: u64,
+    cancelled: AtomicBool,
+    partial_files: Mutex<Vec<PathBuf>>,
+    workspace: PathBuf,
+}
+
+impl JobHandle {
+    /// Creates a new job with a fresh workspace under `workspace_root` and registers it as running.
+    pub fn start(workspace_root: &Path) -> std::io::Result<JobHandle> {
+        let id = NEXT_JOB_ID.fetch_add(1, Ordering::SeqCst);
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+
+        // pid + counter is unique among live instances, the timestamp guards against pid reuse
+        let workspace = workspace_root.join(format!("job-{}-{}-{}", std::process::id(), id, started_at));
+        fs::create_dir_all(&workspace)?;
+
+        let job = JobHandle {
+            inner: Arc::new(JobInner {
+                id,
+                cancelled: AtomicBool::new(false),
+                partial_files: Mutex::new(Vec::new()),
+                workspace,
+            }),
+        };
+
+        if let Ok(mut jobs) = RUNNING_JOBS.lock() {
+            jobs.push(job.clone());
+        }
+        Ok(job)
+    }
+
+    /// Directory reserved for this job's intermediate artifacts.
+    pub fn workspace(&self) -> &Path {
+        &self.inner.workspace
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Remembers a file that is only valid once the job completes, so it can be
+    /// removed if the job is cancelled half way through writing it.
+    pub fn track_file(&self, path: &Path) {
+        if let Ok(mut files) = self.inner.partial_files.lock() {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    /// Deletes every file still tracked by this job.
+    pub fn cleanup_partial_files(&self) {
+        if let Ok(mut files) = self.inner.partial_files.lock() {
+            for path in files.drain(..) {
+                if path.exists() {
+                    if let Err(e) = fs::remove_file(&path) {
+                        eprintln!("Failed to remove partial file {:?}: {}", path, e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits for a child process, killing it if the job gets cancelled.
+    ///
+    /// Returns `Ok(None)` when the process was killed because of cancellation.
+    pub fn wait_for_child(&self, mut child: Child) -> std::io::Result<Option<ExitStatus>> {
+        loop {
+            if self.is_cancelled() {
+                child.kill().ok();
+                child.wait().ok();
+                return Ok(None);
+            }
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Unregisters the job and deletes its workspace once it has completed, failed or been cancelled.
+    pub fn finish(&self) {
+        if let Ok(mut jobs) = RUNNING_JOBS.lock() {
+            jobs.retain(|job| job.inner.id != self.inner.id);
+        }
+
+        if let Err(e) = fs::remove_dir_all(&self.inner.workspace) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                eprintln!("Failed to remove job workspace {:?}: {}", self.inner.workspace, e);
+            }
+        }
+    }
+}
+
+/// Workspaces of other processes are only swept once they are this old, unless the
+/// process is known to be gone.
+const STALE_WORKSPACE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Deletes workspaces left under `workspace_root` by instances that crashed or were
+/// killed before `finish` could run. Called once at startup.
+///
+/// A workspace is stale when the process that created it no longer runs; where that
+/// cannot be checked, when it has not been touched for [`STALE_WORKSPACE_AGE`].
+pub fn sweep_stale_workspaces(workspace_root: &Path) {
+    let Ok(entries) = fs::read_dir(workspace_root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(pid) = name
+            .strip_prefix("job-")
+            .and_then(|rest| rest.split('-').next())
+            .and_then(|pid| pid.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if pid == std::process::id() {
+            continue;
+        }
+
+        let stale = match process_alive(pid) {
+            Some(alive) => !alive,
+            None => entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_WORKSPACE_AGE),
+        };
+        if stale {
+            if let Err(e) = fs::remove_dir_all(entry.path()) {
+                eprintln!("Failed to remove stale job workspace {:?}: {}", entry.path(), e);
+            }
+        }
+    }
+}
+
+/// Whether a process is still running, where the platform lets us tell without extra dependencies.
+fn process_alive(pid: u32) -> Option<bool> {
+    let proc = Path::new("/proc");
+    if cfg!(target_os = "linux") && proc.is_dir() {
+        Some(proc.join(pid.to_string()).exists())
+    } else {
+        None
+    }
+}
+
+/// Cancels every running job and returns how many were signalled.
+pub fn cancel_running_jobs() -> usize {
+    match RUNNING_JOBS.lock() {
+        Ok(jobs) => {
+            for job in jobs.iter() {
+                job.cancel();
+            }
+            jobs.len()
+        }
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_removes_workspaces_of_dead_processes_only() {
+        let root = std::env::temp_dir().join(format!("srtify-sweep-test-{}", std::process::id()));
+        // Above the largest pid Linux hands out, so never a live process
+        let dead = root.join("job-4294967-1-0");
+        let own = root.join(format!("job-{}-1-0", std::process::id()));
+        let other = root.join("notes");
+        for dir in [&dead, &own, &other] {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        sweep_stale_workspaces(&root);
+
+        if cfg!(target_os = "linux") {
+            assert!(!dead.exists());
+        }
+        assert!(own.exists());
+        assert!(other.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::MediaTags;
+use crate::output::{prepare_output, OutputNaming};
+use crate::transcript::{Transcript, Word};
+
+/// LRC specific output settings, persisted as the `lrc` selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LrcOptions {
+    /// Write enhanced LRC `<mm:ss.xx>` tags in front of every word.
+    pub word_timing: bool,
+}
+
+impl Default for LrcOptions {
+    fn default() -> Self {
+        LrcOptions { word_timing: true }
+    }
+}
+
+pub fn create_lrc(
+    transcript: &Transcript,
+    options: &LrcOptions,
+    tags: &MediaTags,
+    naming: &OutputNaming,
+    app: AppHandle,
+) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, "lrc", &app) else {
+        return Ok(String::new());
+    };
+
+    let mut file = File::create(&file_path_buf).map_err(|e| format!("Failed to create LRC file: {}", e))?;
+    file.write_all(render_lrc(transcript, options, tags).as_bytes())
+        .map_err(|e| format!("Failed to write to LRC file: {}", e))?;
+
+    app.emit("subtitle_created", format!("LRC Created :: {}", file_path_buf.display()))
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+
+    Ok(file_path_buf.to_string_lossy().to_string())
+}
+
+/// Renders a transcript's segments as LRC lyrics, one line per segment.
+///
+/// `tags` fill the `[ti:]`, `[ar:]` and `[al:]` ID tags, the transcript's duration `[length:]`.
+pub fn render_lrc(transcript: &Transcript, options: &LrcOptions, tags: &MediaTags) -> String {
+    let mut lrc = String::new();
+    let id_tags = [
+        ("ti", tags.title.clone()),
+        ("ar", tags.artist.clone()),
+        ("al", tags.album.clone()),
+        ("length", transcript.duration_ms.map(ms_to_length)),
+    ];
+    for (key, value) in id_tags {
+        if let Some(value) = value {
+            lrc.push_str(&format!("[{}:{}]\n", key, tag_value(&value)));
+        }
+    }
+    lrc.push_str("[re:SRTify]\n");
+
+    for segment in transcript.cues() {
+        let line = if options.word_timing && !segment.words.is_empty() {
+            enhanced_line(&segment.words, segment.end_ms)
+        } else {
+            single_line(segment.text.trim())
+        };
+        lrc.push_str(&format!("[{}]{}\n", ms_to_lrc_time(segment.start_ms), line));
+    }
+
+    lrc
+}
+
+/// Builds `<00:01.20>Hello <00:01.65>world <00:02.10>`, closing with the end of the last word.
+fn enhanced_line(words: &[Word], cue_end_ms: u64) -> String {
+    let mut line = Vec::new();
+    let mut last_end = None;
+
+    for word in words {
+        let text = word.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        line.push(format!("<{}>{}", ms_to_lrc_time(word.start_ms), single_line(text)));
+        last_end = Some(word.end_ms);
+    }
+    line.push(format!("<{}>", ms_to_lrc_time(last_end.unwrap_or(cue_end_ms))));
+
+    line.join(" ")
+}
+
+/// `mm:ss.xx`; minutes keep counting past 59 since LRC has no hour field.
+fn ms_to_lrc_time(ms: u64) -> String {
+    let total_cs = (ms + 5) / 10;
+    format!("{:02}:{:02}.{:02}", total_cs / 6000, (total_cs % 6000) / 100, total_cs % 100)
+}
+
+fn ms_to_length(ms: u64) -> String {
+    let total = (ms + 500) / 1000;
+    format!("{:02}:{:02}", total / 60, total % 60)
+}
+
+/// Lyrics are line based and `[`/`]` delimit tags, so neither may leak into a value.
+fn tag_value(value: &str) -> String {
+    single_line(value).replace('[', "(").replace(']', ")")
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Segment;
+
+    fn word(text: &str, start_ms: u64, end_ms: u64) -> Word {
+        Word {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn times_round_to_centiseconds() {
+        assert_eq!(ms_to_lrc_time(0), "00:00.00");
+        assert_eq!(ms_to_lrc_time(1204), "00:01.20");
+        assert_eq!(ms_to_lrc_time(1205), "00:01.21");
+        assert_eq!(ms_to_lrc_time(59_994), "00:59.99");
+        assert_eq!(ms_to_lrc_time(59_995), "01:00.00");
+        assert_eq!(ms_to_lrc_time(3_723_450), "62:03.45");
+    }
+
+    #[test]
+    fn id_tags_open_the_file() {
+        let tags = MediaTags {
+            title: Some("Song [live]".to_string()),
+            artist: Some("Ann\nBob".to_string()),
+            album: None,
+        };
+        let mut transcript = Transcript::new(Vec::new());
+        transcript.duration_ms = Some(185_400);
+        assert_eq!(
+            render_lrc(&transcript, &LrcOptions::default(), &tags),
+            "[ti:Song (live)]\n[ar:Ann Bob]\n[length:03:05]\n[re:SRTify]\n"
+        );
+    }
+
+    #[test]
+    fn cues_past_an_hour_keep_counting_minutes() {
+        let transcript = Transcript::new(vec![Segment {
+            start_ms: 3_700_000,
+            end_ms: 3_702_500,
+            text: "Late night".to_string(),
+            words: vec![word(" Late", 3_700_000, 3_700_900), word(" night", 3_701_200, 3_702_004)],
+            ..Default::default()
+        }]);
+        let tags = MediaTags::default();
+
+        let enhanced = render_lrc(&transcript, &LrcOptions::default(), &tags);
+        assert!(enhanced.ends_with("[re:SRTify]\n[61:40.00]<61:40.00>Late <61:41.20>night <61:42.00>\n"), "{}", enhanced);
+        let plain = render_lrc(&transcript, &LrcOptions { word_timing: false }, &tags);
+        assert!(plain.ends_with("[re:SRTify]\n[61:40.00]Late night\n"), "{}", plain);
+    }
+}
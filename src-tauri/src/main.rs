@@ -4,13 +4,48 @@
 mod transcriber;
 mod local_server;
 mod utils;
+mod job;
+mod audio;
+mod output;
+mod vtt;
+mod ass;
+mod ttml;
+mod text_export;
+mod lrc;
+mod shaping;
+mod timing;
+mod transcript;
+mod import;
+mod convert;
+mod retime;
+mod models;
+mod download;
+mod model_cache;
+mod model_bundle;
 use tauri_plugin_opener;
+use tauri::Manager;
 
 #[tokio::main]
 async fn main() {
+    // `srtify convert ...` converts subtitle files and `srtify models pin ...` pins model
+    // checksums, both without opening a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("convert") => std::process::exit(convert::run_cli(&args[1..], convert::ConversionSettings::from_selections())),
+        Some("models") => std::process::exit(models::run_cli(&args[1..]).await),
+        _ => {}
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .setup(|_app| {
+        .setup(|app| {
+            // Workspaces of instances that crashed mid-job are never cleaned up by `finish`
+            if let Ok(cache_dir) = app.path().app_cache_dir() {
+                job::sweep_stale_workspaces(&cache_dir.join("jobs"));
+            }
+            models::migrate_legacy_overrides(app.handle());
+            model_cache::migrate_legacy_models(app.handle());
+
             // Spawn the local HTTP server
             tokio::spawn(async {
                 local_server::start_server().await;
@@ -24,7 +59,18 @@ async fn main() {
             utils::save_selection,
             utils::load_selection,
             transcriber::start_transcription,
-            transcriber::stop_transcription
+            transcriber::stop_transcription,
+            import::import_subtitle,
+            convert::convert_subtitle,
+            convert::retime_subtitle,
+            models::list_registry_models,
+            models::verify_models,
+            model_cache::list_models,
+            model_cache::import_model,
+            model_cache::delete_model,
+            model_cache::model_disk_usage,
+            model_bundle::export_model_bundle,
+            model_bundle::import_model_bundle
         ])
         .run(tauri::generate_context!())
         .expect("error while running Tauri application");
@@ -0,0 +1,492 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::download::discard_partial;
+use crate::model_cache::{cached_model, models_dir, CachedModel};
+use crate::models::{check_magic, overrides_path, partial_path, ModelEntry, ModelRegistry};
+
+/// Version of the bundle layout: a tar archive holding `manifest.json` followed by `models/<file_name>`.
+pub const BUNDLE_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const MODELS_FOLDER: &str = "models/";
+
+/// `manifest.json` of a bundle, shaped like the registry manifest so it can be merged
+/// into the overrides file as is. Every entry carries the exact size and SHA-256 of
+/// the file in the archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BundleManifest {
+    version: u32,
+    models: Vec<ModelEntry>,
+}
+
+/// What went into an exported bundle.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleReport {
+    pub path: String,
+    pub models: Vec<String>,
+    /// Size of the archive in bytes.
+    pub size: u64,
+}
+
+/// Writes installed registry models (all of them, or just `names`) and their registry
+/// entries into a single archive that `import_model_bundle` can install on a machine
+/// without network access.
+///
+/// Every model is verified first, so a corrupt cache cannot be passed on.
+#[tauri::command]
+pub async fn export_model_bundle(app: AppHandle, path: String, names: Option<Vec<String>>) -> Result<BundleReport, String> {
+    let registry = ModelRegistry::load(&app)?;
+    let dir = models_dir(&app)?;
+    let entries: Vec<ModelEntry> = match &names {
+        Some(names) => names
+            .iter()
+            .map(|name| registry.get(name).cloned().ok_or_else(|| format!("Unknown model: {}", name)))
+            .collect::<Result<_, _>>()?,
+        None => registry
+            .entries()
+            .iter()
+            .filter(|entry| dir.join(&entry.file_name).exists())
+            .cloned()
+            .collect(),
+    };
+    if entries.is_empty() {
+        return Err("No downloaded models to export".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let report = write_bundle(Path::new(&path), &dir, entries, &|message| emit_info(&app, message))?;
+        app.emit("success", format!("Exported {} model(s) to {}", report.models.len(), report.path))
+            .unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+        Ok(report)
+    })
+    .await
+    .map_err(|e| format!("Exporting models failed: {}", e))?
+}
+
+/// Installs the models of a bundle into the model cache, checking each against the
+/// size and SHA-256 recorded in the bundle (and in the local registry, where it knows
+/// the model). Models the local registry does not know are added to the overrides
+/// file so they can be picked by name.
+#[tauri::command]
+pub async fn import_model_bundle(app: AppHandle, path: String) -> Result<Vec<CachedModel>, String> {
+    let registry = ModelRegistry::load(&app)?;
+    let dir = models_dir(&app)?;
+    let overrides = overrides_path(&app)?;
+    tokio::task::spawn_blocking(move || {
+        let path = Path::new(&path);
+        let models = read_bundle(path, &dir, &registry, &overrides, &|message| emit_info(&app, message))?;
+        app.emit("success", format!("Imported {} model(s) from {}", models.len(), path.display()))
+            .unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+        Ok(models)
+    })
+    .await
+    .map_err(|e| format!("Importing models failed: {}", e))?
+}
+
+fn emit_info(app: &AppHandle, message: String) {
+    app.emit("info", message).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+}
+
+/// Writes the bundle for `entries` of the model cache in `dir`; `info` reports progress.
+fn write_bundle(path: &Path, dir: &Path, entries: Vec<ModelEntry>, info: &dyn Fn(String)) -> Result<BundleReport, String> {
+    let mut models = Vec::new();
+    for mut entry in entries {
+        let model_path = dir.join(&entry.file_name);
+        if !model_path.exists() {
+            return Err(format!("{} has not been downloaded", entry.name));
+        }
+        info(format!("Verifying {}", entry.name));
+        let sha256 = entry
+            .verify_file(&model_path)
+            .map_err(|e| format!("Cannot export {}: {}", entry.name, e))?;
+        entry.size = Some(fs::metadata(&model_path).map_err(|e| format!("Failed to read {}: {}", model_path.display(), e))?.len());
+        entry.sha256 = Some(sha256);
+        models.push(entry);
+    }
+
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        models,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| format!("Failed to write bundle manifest: {}", e))?;
+
+    // Written next to the target first so a failed export never leaves a truncated bundle behind
+    let part_path = partial_path(path);
+    let io_error = |e: std::io::Error| format!("Failed to write {}: {}", part_path.display(), e);
+    let result = (|| {
+        let mut archive = tar::Builder::new(File::create(&part_path).map_err(io_error)?);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, MANIFEST_NAME, json.as_slice()).map_err(io_error)?;
+
+        for entry in &manifest.models {
+            info(format!("Adding {} to the bundle", entry.name));
+            let mut file = File::open(dir.join(&entry.file_name)).map_err(io_error)?;
+            archive
+                .append_file(format!("{}{}", MODELS_FOLDER, entry.file_name), &mut file)
+                .map_err(io_error)?;
+        }
+        archive.into_inner().and_then(|file| file.sync_all()).map_err(io_error)
+    })();
+    if let Err(e) = result {
+        fs::remove_file(&part_path).ok();
+        return Err(e);
+    }
+    fs::rename(&part_path, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(BundleReport {
+        path: path.to_string_lossy().to_string(),
+        models: manifest.models.iter().map(|entry| entry.name.clone()).collect(),
+        size: fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0),
+    })
+}
+
+/// Installs a bundle into the model cache in `dir`, merging entries the registry does not
+/// know into the `overrides` file; `info` reports progress.
+///
+/// Every model is first streamed into a `.part` file and checked. Only once the whole
+/// bundle has passed are the overrides written and the models renamed into place, so a
+/// bad model late in the archive leaves nothing half installed.
+fn read_bundle(
+    path: &Path,
+    dir: &Path,
+    registry: &ModelRegistry,
+    overrides: &Path,
+    info: &dyn Fn(String),
+) -> Result<Vec<CachedModel>, String> {
+    let mut staged = Vec::new();
+    let result = stage_bundle(path, dir, registry, info, &mut staged);
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            for (_, part_path) in &staged {
+                discard_partial(part_path);
+            }
+            return Err(e);
+        }
+    };
+
+    let unknown: Vec<&ModelEntry> = manifest.models.iter().filter(|model| registry.get(&model.name).is_none()).collect();
+    if !unknown.is_empty() {
+        if let Err(e) = add_overrides(overrides, &unknown) {
+            for (_, part_path) in &staged {
+                discard_partial(part_path);
+            }
+            return Err(e);
+        }
+    }
+
+    for (model, part_path) in &staged {
+        let target = dir.join(&model.file_name);
+        fs::rename(part_path, &target).map_err(|e| format!("Failed to install {}: {}", model.name, e))?;
+    }
+    for model in &manifest.models {
+        // Registry entries without a pinned size or checksum are checked against this on use
+        registry.get(&model.name).unwrap_or(model).record_verified(
+            &dir.join(&model.file_name),
+            model.size.unwrap_or(0),
+            model.sha256.as_deref().unwrap_or_default(),
+        )?;
+    }
+
+    Ok(manifest
+        .models
+        .iter()
+        .map(|model| cached_model(dir, &model.file_name, Some(model)))
+        .collect())
+}
+
+/// Reads and checks the whole bundle, writing models that are not installed yet to
+/// `.part` files listed in `staged`.
+fn stage_bundle(
+    path: &Path,
+    dir: &Path,
+    registry: &ModelRegistry,
+    info: &dyn Fn(String),
+    staged: &mut Vec<(ModelEntry, PathBuf)>,
+) -> Result<BundleManifest, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let read_error = |e: std::io::Error| format!("Failed to read bundle {}: {}", path.display(), e);
+    let mut archive = tar::Archive::new(file);
+    let mut entries = archive.entries().map_err(read_error)?;
+
+    // The manifest comes first so every model can be checked while it streams past
+    let manifest = match entries.next() {
+        Some(entry) => {
+            let mut entry = entry.map_err(read_error)?;
+            if entry.path().map_err(read_error)?.as_ref() != Path::new(MANIFEST_NAME) {
+                return Err(format!("{} is not a model bundle", path.display()));
+            }
+            let mut json = String::new();
+            entry.read_to_string(&mut json).map_err(read_error)?;
+            parse_manifest(&json, registry)?
+        }
+        None => return Err(format!("{} is empty", path.display())),
+    };
+
+    let mut seen: Vec<String> = Vec::new();
+    for entry in entries {
+        let mut entry = entry.map_err(read_error)?;
+        let entry_path = entry.path().map_err(read_error)?.to_string_lossy().to_string();
+        let model = entry_path
+            .strip_prefix(MODELS_FOLDER)
+            .and_then(|file_name| manifest.models.iter().find(|model| model.file_name == file_name))
+            .ok_or_else(|| format!("Unexpected file in model bundle: {}", entry_path))?;
+        if seen.contains(&model.name) {
+            return Err(format!("{} appears twice in the bundle", model.name));
+        }
+        seen.push(model.name.clone());
+
+        // A file of the right size can still be corrupt, so only the checksum counts as installed
+        let target = dir.join(&model.file_name);
+        match target.exists().then(|| model.verify_file(&target)) {
+            Some(Ok(_)) => {
+                info(format!("{} is already installed", model.name));
+                continue;
+            }
+            Some(Err(e)) => info(format!("Replacing installed {}: {}", model.name, e)),
+            None => info(format!("Installing {}", model.name)),
+        }
+        let part_path = partial_path(&target);
+        stage_model(&mut entry, model, &part_path)?;
+        staged.push((model.clone(), part_path));
+    }
+
+    let missing: Vec<&str> = manifest
+        .models
+        .iter()
+        .filter(|model| !seen.contains(&model.name))
+        .map(|model| model.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("The bundle is incomplete, missing: {}", missing.join(", ")));
+    }
+    Ok(manifest)
+}
+
+/// Parses and checks a bundle manifest before any model data is read.
+fn parse_manifest(json: &str, registry: &ModelRegistry) -> Result<BundleManifest, String> {
+    let manifest: BundleManifest = serde_json::from_str(json).map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest.version == 0 || manifest.version > BUNDLE_VERSION {
+        return Err(format!(
+            "Unsupported model bundle version {} (expected 1..={})",
+            manifest.version, BUNDLE_VERSION
+        ));
+    }
+    for model in &manifest.models {
+        model.validate()?;
+        if model.size.is_none() || model.sha256.is_none() {
+            return Err(format!("Bundle entry {} has no size or SHA-256", model.name));
+        }
+        if let Some(known) = registry.get(&model.name) {
+            if known.file_name != model.file_name {
+                return Err(format!(
+                    "Bundled {} is stored as {}, but the registry expects {}",
+                    model.name, model.file_name, known.file_name
+                ));
+            }
+            // The local registry has the last word on what the model must hash to
+            known.check(model.size.unwrap_or(0), model.sha256.as_deref())?;
+        }
+    }
+    Ok(manifest)
+}
+
+/// Streams one archive entry into `part_path`, hashing it on the way, and checks it
+/// against the manifest; a file that does not match is deleted.
+fn stage_model(source: &mut impl Read, model: &ModelEntry, part_path: &Path) -> Result<(), String> {
+    discard_partial(part_path);
+
+    let result = (|| {
+        let io_error = |e: std::io::Error| format!("Failed to write {}: {}", part_path.display(), e);
+        let mut file = File::create(part_path).map_err(io_error)?;
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut buffer = vec![0; 1 << 20];
+        loop {
+            let read = source
+                .read(&mut buffer)
+                .map_err(|e| format!("Failed to read {} from the bundle: {}", model.name, e))?;
+            if read == 0 {
+                break;
+            }
+            file.write_all(&buffer[..read]).map_err(io_error)?;
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+        file.sync_all().map_err(io_error)?;
+        model.check(size, Some(&format!("{:x}", hasher.finalize())))?;
+        check_magic(part_path)
+    })();
+    result.map_err(|e| {
+        discard_partial(part_path);
+        format!("Bundled {} is corrupt: {}", model.name, e)
+    })
+}
+
+/// Adds bundle entries for models the local registry does not know to the overrides file.
+fn add_overrides(path: &Path, models: &[&ModelEntry]) -> Result<(), String> {
+    let mut overrides: Value = match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?,
+        Err(_) => serde_json::json!({ "version": 1, "models": [] }),
+    };
+    let Some(Value::Array(entries)) = overrides.get_mut("models") else {
+        return Err(format!("Invalid {}: expected a \"models\" array of objects", path.display()));
+    };
+    for model in models {
+        let value = serde_json::to_value(model).map_err(|e| e.to_string())?;
+        match entries.iter_mut().find(|entry| entry["name"] == model.name.as_str()) {
+            Some(entry) => *entry = value,
+            None => entries.push(value),
+        }
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let json = serde_json::to_string_pretty(&overrides).map_err(|e| e.to_string())?;
+
+    // Write through a per-process temp file and rename, so a crash never leaves a half written file
+    let temp_path = dir.join(format!("models.json.{}.tmp", std::process::id()));
+    let write_error = |e: std::io::Error| format!("Failed to write {}: {}", path.display(), e);
+    fs::write(&temp_path, json).map_err(write_error)?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        fs::remove_file(&temp_path).ok();
+        write_error(e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+
+    /// A manifest entry for a model file holding `content`.
+    fn bundle_entry(name: &str, file_name: &str, content: &[u8]) -> Value {
+        json!({
+            "name": name,
+            "file_name": file_name,
+            "url": format!("https://example.com/{}", file_name),
+            "size": content.len(),
+            "sha256": format!("{:x}", Sha256::digest(content)),
+            "multilingual": true,
+        })
+    }
+
+    /// Writes a bundle by hand, so the tests can put in what `write_bundle` never would.
+    fn pack(path: &Path, manifest: &Value, files: &[(&str, &[u8])]) {
+        let mut archive = tar::Builder::new(File::create(path).unwrap());
+        let json = serde_json::to_vec(manifest).unwrap();
+        let entries = [(MANIFEST_NAME.to_string(), json.as_slice())]
+            .into_iter()
+            .chain(files.iter().map(|&(file_name, content)| (format!("{}{}", MODELS_FOLDER, file_name), content)));
+        for (name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, name, content).unwrap();
+        }
+        archive.finish().unwrap();
+    }
+
+    #[test]
+    fn exported_bundles_install_unknown_models_as_overrides() {
+        let root = env::temp_dir().join(format!("srtify-bundle-test-{}", std::process::id()));
+        let (source, dir) = (root.join("source"), root.join("models"));
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let source_overrides = root.join("source.json");
+        fs::write(
+            &source_overrides,
+            serde_json::to_string(&json!({ "models": [bundle_entry("custom", "ggml-custom.bin", b"lmggcustom")] })).unwrap(),
+        )
+        .unwrap();
+        let source_registry = ModelRegistry::with_overrides(&source_overrides).unwrap();
+        fs::write(source.join("ggml-tiny.bin"), "lmggtiny").unwrap();
+        fs::write(source.join("ggml-custom.bin"), "lmggcustom").unwrap();
+
+        let path = root.join("models.srtify-bundle");
+        let entries = ["whisper-tiny", "custom"].map(|name| source_registry.get(name).unwrap().clone());
+        let report = write_bundle(&path, &source, entries.to_vec(), &|_| {}).unwrap();
+        assert_eq!(report.models, ["whisper-tiny", "custom"]);
+
+        let overrides = root.join("models.json");
+        let registry = ModelRegistry::with_overrides(&overrides).unwrap();
+        let installed = read_bundle(&path, &dir, &registry, &overrides, &|_| {}).unwrap();
+        assert_eq!(installed.len(), 2);
+
+        // The unpinned bundled model is recorded with the bundle's checksum
+        let tiny = registry.get("whisper-tiny").unwrap();
+        assert!(tiny.sha256.is_none());
+        assert!(tiny.check_cached(&dir.join("ggml-tiny.bin")).is_ok());
+        // The unknown model can now be picked by name
+        let registry = ModelRegistry::with_overrides(&overrides).unwrap();
+        let custom = registry.get("custom").unwrap();
+        assert_eq!(custom.file_name, "ggml-custom.bin");
+        assert!(custom.check_cached(&dir.join("ggml-custom.bin")).is_ok());
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn rejected_bundles_install_nothing() {
+        let root = env::temp_dir().join(format!("srtify-bundle-reject-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let registry = ModelRegistry::with_overrides(&root.join("missing.json")).unwrap();
+        let tiny = bundle_entry("whisper-tiny", "ggml-tiny.bin", b"lmggtiny");
+        let custom = bundle_entry("custom", "ggml-custom.bin", b"lmggcustom");
+        let manifest = |version: u32, models: &[&Value]| json!({ "version": version, "models": models });
+        let mut misnamed = tiny.clone();
+        misnamed["file_name"] = json!("ggml-tiny-copy.bin");
+
+        // Name, manifest, archived files and the expected error
+        type Case<'a> = (&'a str, Value, &'a [(&'a str, &'a [u8])], &'a str);
+        let cases: [Case; 5] = [
+            (
+                "corrupt",
+                manifest(1, &[&tiny, &custom]),
+                &[("ggml-tiny.bin", b"lmggtiny"), ("ggml-custom.bin", b"lmggcustoM")],
+                "Bundled custom is corrupt",
+            ),
+            (
+                "truncated",
+                manifest(1, &[&tiny, &custom]),
+                &[("ggml-tiny.bin", b"lmggtiny"), ("ggml-custom.bin", b"lmggcus")],
+                "ggml-custom.bin is 7 bytes, expected 10",
+            ),
+            ("missing", manifest(1, &[&tiny, &custom]), &[("ggml-tiny.bin", b"lmggtiny")], "missing: custom"),
+            (
+                "misnamed",
+                manifest(1, &[&misnamed]),
+                &[("ggml-tiny-copy.bin", b"lmggtiny")],
+                "the registry expects ggml-tiny.bin",
+            ),
+            ("future", manifest(2, &[&tiny]), &[("ggml-tiny.bin", b"lmggtiny")], "Unsupported model bundle version 2"),
+        ];
+        for (name, manifest, files, error) in cases {
+            let (path, dir, overrides) = (root.join(format!("{}.tar", name)), root.join(name), root.join(format!("{}.json", name)));
+            fs::create_dir_all(&dir).unwrap();
+            pack(&path, &manifest, files);
+
+            let result = read_bundle(&path, &dir, &registry, &overrides, &|_| {});
+            assert!(result.as_ref().is_err_and(|e| e.contains(error)), "{}: {:?}", name, result);
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "{}", name);
+            assert!(!overrides.exists(), "{}", name);
+        }
+        fs::remove_dir_all(&root).ok();
+    }
+}
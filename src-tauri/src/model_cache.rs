@@ -0,0 +1,411 @@
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::download::discard_partial;
+use crate::models::{
+    check_magic, is_plain_file_name, partial_path, sha256_file, verified_path, ModelEntry, ModelRegistry,
+};
+
+/// A registry model or a file found in the model cache.
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedModel {
+    /// Registry name; `None` for an imported file the registry does not know.
+    pub name: Option<String>,
+    pub file_name: String,
+    pub path: String,
+    pub installed: bool,
+    /// True when the cache holds a link to a model file kept elsewhere.
+    pub linked: bool,
+    /// Size of the installed file in bytes, 0 when not installed.
+    pub size: u64,
+    /// Bytes of an unfinished download waiting to be resumed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_size: Option<u64>,
+    /// Size the registry expects, so the UI can show what a download will cost.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u32>,
+}
+
+/// Space taken by the model cache.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDiskUsage {
+    pub path: String,
+    /// Bytes used by the cache folder; linked models are not counted.
+    pub total_size: u64,
+    /// Installed models, linked ones included.
+    pub models: usize,
+    /// Part of `total_size` held by unfinished downloads.
+    pub partial_size: u64,
+}
+
+/// Folder downloaded and imported models are kept in.
+///
+/// It lives in the app data dir, where the OS does not clean up behind the user's back;
+/// models downloaded by older versions into the temp dir are moved over at startup by
+/// [`migrate_legacy_models`].
+pub fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?
+        .join("models");
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create model folder: {}", e))?;
+    Ok(dir)
+}
+
+/// Lists every registry model, installed or not, followed by any other model files in the cache.
+#[tauri::command]
+pub fn list_models(app: AppHandle) -> Result<Vec<CachedModel>, String> {
+    let registry = ModelRegistry::load(&app)?;
+    let dir = models_dir(&app)?;
+
+    let mut models: Vec<CachedModel> = registry
+        .entries()
+        .iter()
+        .map(|entry| cached_model(&dir, &entry.file_name, Some(entry)))
+        .collect();
+
+    for file_name in model_files(&dir)? {
+        if !registry.entries().iter().any(|entry| entry.file_name == file_name) {
+            models.push(cached_model(&dir, &file_name, None));
+        }
+    }
+    Ok(models)
+}
+
+/// Copies (or, with `link`, symlinks) a local whisper.cpp model into the cache.
+///
+/// The file is matched to a registry entry by `name`, by its SHA-256 or by its file name,
+/// and must then have the size and checksum the registry expects. Files the registry
+/// does not know are imported under their own name and can be picked by path.
+#[tauri::command]
+pub async fn import_model(
+    app: AppHandle,
+    path: String,
+    name: Option<String>,
+    link: Option<bool>,
+) -> Result<CachedModel, String> {
+    let source = PathBuf::from(&path);
+    if !source.is_file() {
+        return Err(format!("Model file not found at path: {}", path));
+    }
+    check_magic(&source)?;
+
+    let registry = ModelRegistry::load(&app)?;
+    let named = match &name {
+        Some(name) => Some(registry.get(name).ok_or_else(|| format!("Unknown model: {}", name))?),
+        None => None,
+    };
+
+    app.emit("info", format!("Verifying {}", path)).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+    let hashed = source.clone();
+    let (size, sha256) = tokio::task::spawn_blocking(move || -> Result<(u64, String), String> {
+        let size = fs::metadata(&hashed)
+            .map_err(|e| format!("Failed to read {}: {}", hashed.display(), e))?
+            .len();
+        Ok((size, sha256_file(&hashed)?))
+    })
+    .await
+    .map_err(|e| format!("Verification of {} failed: {}", path, e))??;
+
+    let source_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let entry = named
+        .or_else(|| {
+            registry
+                .entries()
+                .iter()
+                .find(|entry| entry.sha256.as_deref().is_some_and(|expected| expected.eq_ignore_ascii_case(&sha256)))
+        })
+        .or_else(|| registry.entries().iter().find(|entry| entry.file_name == source_name));
+
+    let file_name = match entry {
+        Some(entry) => {
+            entry.check(size, Some(&sha256))?;
+            entry.file_name.clone()
+        }
+        None if is_plain_file_name(&source_name) && source_name.ends_with(".bin") => source_name,
+        None => return Err(format!("{} is not a .bin model file", path)),
+    };
+
+    let dir = models_dir(&app)?;
+    let target = dir.join(&file_name);
+    if fs::symlink_metadata(&target).is_ok() {
+        return Err(format!("{} is already installed, delete it first to replace it", file_name));
+    }
+
+    if link.unwrap_or(false) {
+        let source = source
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {}", path, e))?;
+        symlink(&source, &target).map_err(|e| {
+            format!("Failed to link {} into the model cache (copy it instead): {}", path, e)
+        })?;
+    } else {
+        let part_path = partial_path(&target);
+        discard_partial(&part_path);
+        let copied = part_path.clone();
+        let result = tokio::task::spawn_blocking(move || fs::copy(&source, &copied))
+            .await
+            .map_err(|e| format!("Copying {} failed: {}", path, e))?;
+        match result {
+            Ok(copied) if copied == size => {}
+            Ok(copied) => {
+                discard_partial(&part_path);
+                return Err(format!("Copied {} of {} bytes of {}", copied, size, path));
+            }
+            Err(e) => {
+                discard_partial(&part_path);
+                return Err(format!("Failed to copy {}: {}", path, e));
+            }
+        }
+        fs::rename(&part_path, &target).map_err(|e| format!("Failed to install {}: {}", file_name, e))?;
+    }
+
+    if let Some(entry) = entry {
+        entry.record_verified(&target, size, &sha256)?;
+    }
+
+    let label = entry.map_or(file_name.as_str(), |entry| entry.name.as_str());
+    app.emit("success", format!("Imported model {} (SHA-256 {})", label, sha256)).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+    Ok(cached_model(&dir, &file_name, entry))
+}
+
+/// Deletes an installed model, by registry name or cache file name, together with any
+/// unfinished download of it. Returns the number of bytes freed.
+#[tauri::command]
+pub fn delete_model(app: AppHandle, name: String) -> Result<u64, String> {
+    let registry = ModelRegistry::load(&app)?;
+    let file_name = match registry.get(&name) {
+        Some(entry) => entry.file_name.clone(),
+        None if is_plain_file_name(&name) => name.clone(),
+        None => return Err(format!("Unknown model: {}", name)),
+    };
+
+    let dir = models_dir(&app)?;
+    let target = dir.join(&file_name);
+    let part_path = partial_path(&target);
+    let freed = disk_size(&target) + disk_size(&part_path);
+    let installed = fs::symlink_metadata(&target).is_ok();
+    if !installed && !part_path.exists() {
+        return Err(format!("{} is not installed", name));
+    }
+
+    if installed {
+        fs::remove_file(&target).map_err(|e| format!("Failed to delete {}: {}", target.display(), e))?;
+    }
+    fs::remove_file(verified_path(&target)).ok();
+    discard_partial(&part_path);
+
+    app.emit("success", format!("Deleted model {}", name)).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+    Ok(freed)
+}
+
+/// Reports how much disk space the model cache takes.
+#[tauri::command]
+pub fn model_disk_usage(app: AppHandle) -> Result<ModelDiskUsage, String> {
+    let dir = models_dir(&app)?;
+    let mut usage = ModelDiskUsage {
+        path: dir.to_string_lossy().to_string(),
+        total_size: 0,
+        models: 0,
+        partial_size: 0,
+    };
+
+    let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let size = disk_size(&path);
+        usage.total_size += size;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("bin") => usage.models += 1,
+            Some("part") => usage.partial_size += size,
+            _ => {}
+        }
+    }
+    Ok(usage)
+}
+
+/// Status of one cache file, described by its registry entry when there is one.
+pub fn cached_model(dir: &Path, file_name: &str, entry: Option<&ModelEntry>) -> CachedModel {
+    let path = dir.join(file_name);
+    let linked = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+    let size = fs::metadata(&path).map(|metadata| metadata.len()).ok();
+    CachedModel {
+        name: entry.map(|entry| entry.name.clone()),
+        file_name: file_name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        installed: size.is_some(),
+        linked,
+        size: size.unwrap_or(0),
+        partial_size: fs::metadata(partial_path(&path)).map(|metadata| metadata.len()).ok(),
+        expected_size: entry.and_then(|entry| entry.size),
+        memory_mb: entry.and_then(|entry| entry.memory_mb),
+    }
+}
+
+/// Names of the `.bin` files in the cache, sorted.
+fn model_files(dir: &Path) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+    let mut files: Vec<String> = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.ends_with(".bin"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Bytes a cache entry occupies; a symlink's target lives elsewhere and is not counted.
+fn disk_size(path: &Path) -> u64 {
+    fs::symlink_metadata(path)
+        .ok()
+        .filter(|metadata| !metadata.file_type().is_symlink())
+        .map_or(0, |metadata| metadata.len())
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(source, target)
+}
+
+/// Moves models downloaded into the old temp-dir cache. Called once at startup.
+pub fn migrate_legacy_models(app: &AppHandle) {
+    let moved = models_dir(app).and_then(|dir| {
+        let registry = ModelRegistry::load(app)?;
+        move_legacy_models(&env::temp_dir().join("srtify"), &dir, &registry);
+        Ok(())
+    });
+    if let Err(e) = moved {
+        eprintln!("Failed to migrate models from the temp folder: {}", e);
+    }
+}
+
+/// Moves the `.bin` files in `legacy` into `dir`. Nothing in the temp dir says whether a
+/// download there ever finished, so files without the ggml magic number, or that do not
+/// match the size and checksum the registry pins, are deleted rather than moved; registry
+/// models are hashed either way and recorded so [`ModelEntry::check_cached`] accepts them.
+/// Best effort: whatever cannot be moved stays put and is simply downloaded again when needed.
+fn move_legacy_models(legacy: &Path, dir: &Path, registry: &ModelRegistry) {
+    let Ok(entries) = fs::read_dir(legacy) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("bin") || !path.is_file() {
+            continue;
+        }
+        let target = dir.join(entry.file_name());
+        if target.exists() {
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let model = registry.entries().iter().find(|model| model.file_name == file_name);
+        let verified = check_magic(&path).and_then(|()| match model {
+            // Without a pin the hash is only compared with itself, but recording it keeps
+            // later runs from discarding the model as unverified
+            Some(model) => model.verify_file(&path).map(|sha256| Some((model, sha256))),
+            None => Ok(None),
+        });
+        let verified = match verified {
+            Ok(verified) => verified,
+            Err(e) => {
+                eprintln!("Deleting {} instead of migrating it: {}", path.display(), e);
+                fs::remove_file(&path).ok();
+                continue;
+            }
+        };
+        // The temp dir is often on another file system, where only a copy will do
+        let moved = fs::rename(&path, &target).or_else(|_| {
+            let part_path = partial_path(&target);
+            fs::copy(&path, &part_path)
+                .and_then(|_| fs::rename(&part_path, &target))
+                .and_then(|_| fs::remove_file(&path))
+                .inspect_err(|_| discard_partial(&part_path))
+        });
+        if let Err(e) = moved {
+            eprintln!("Failed to move {} to {}: {}", path.display(), target.display(), e);
+            continue;
+        }
+        if let Some((model, sha256)) = verified {
+            let size = fs::metadata(&target).map(|metadata| metadata.len()).unwrap_or(0);
+            model.record_verified(&target, size, &sha256).unwrap_or_else(|e| eprintln!("{}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_models_are_verified_before_they_are_moved() {
+        let root = env::temp_dir().join(format!("srtify-model-cache-test-{}", std::process::id()));
+        let (legacy, dir) = (root.join("legacy"), root.join("models"));
+        fs::create_dir_all(&legacy).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let overrides = root.join("models.json");
+        fs::write(
+            &overrides,
+            r#"{ "models": [
+                { "name": "whisper-tiny", "size": 11 }, { "name": "whisper-base", "size": 20 },
+                { "name": "whisper-small.en", "size": null, "sha256": null }
+            ] }"#,
+        )
+        .unwrap();
+        let registry = ModelRegistry::with_overrides(&overrides).unwrap();
+
+        for (file_name, content) in [
+            ("ggml-tiny.bin", "lmggweights"),
+            ("ggml-small.en.bin", "lmggenglish"),
+            ("ggml-base.bin", "lmggweights"),
+            ("ggml-small.bin", "<html>404</html>"),
+            ("ggml-mine.bin", "lmggmine"),
+            ("ggml-medium.bin", "lmggold"),
+        ] {
+            fs::write(legacy.join(file_name), content).unwrap();
+        }
+        fs::write(dir.join("ggml-medium.bin"), "lmggnew").unwrap();
+
+        move_legacy_models(&legacy, &dir, &registry);
+
+        // Pinned and intact: moved, with the checksum recorded for later runs
+        assert_eq!(fs::read_to_string(dir.join("ggml-tiny.bin")).unwrap(), "lmggweights");
+        assert!(registry.get("whisper-tiny").unwrap().check_cached(&dir.join("ggml-tiny.bin")).is_ok());
+        assert!(verified_path(&dir.join("ggml-tiny.bin")).exists());
+        // Bundled but unpinned: moved and usable without being downloaded again
+        let small_en = registry.get("whisper-small.en").unwrap();
+        assert!(small_en.sha256.is_none());
+        assert!(small_en.check_cached(&dir.join("ggml-small.en.bin")).is_ok());
+        assert!(!legacy.join("ggml-small.en.bin").exists());
+        // Truncated or not a model at all: deleted
+        for file_name in ["ggml-base.bin", "ggml-small.bin"] {
+            assert!(!legacy.join(file_name).exists() && !dir.join(file_name).exists(), "{}", file_name);
+        }
+        // Unknown to the registry: moved, to be picked by path
+        assert!(dir.join("ggml-mine.bin").exists());
+        // Already installed: both copies left alone
+        assert_eq!(fs::read_to_string(dir.join("ggml-medium.bin")).unwrap(), "lmggnew");
+        assert!(legacy.join("ggml-medium.bin").exists());
+        fs::remove_dir_all(&root).ok();
+    }
+}
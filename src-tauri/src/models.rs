@@ -0,0 +1,710 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::download::{discard_partial, Downloaded};
+use crate::model_cache::models_dir;
+
+/// Manifest compiled into the app; entries can be changed or added through [`overrides_path`].
+const BUNDLED_MANIFEST: &str = include_str!("../models.json");
+
+/// First bytes of every whisper.cpp model: the `ggml` magic number, little endian.
+const GGML_MAGIC: &[u8; 4] = b"lmgg";
+
+/// One downloadable whisper.cpp checkpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelEntry {
+    /// Name picked in the UI and stored in the `model` selection, e.g. `whisper-base`.
+    pub name: String,
+    /// File name inside the model cache.
+    pub file_name: String,
+    pub url: String,
+    /// Exact file size in bytes, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// Lowercase hex SHA-256 of the file, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub multilingual: bool,
+    /// `q5_0`, `q8_0`, ...; unset for the full precision f16 files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantization: Option<String>,
+    /// Approximate memory needed to run the model, in MB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u32>,
+    /// English-only checkpoint used instead when transcribing English, e.g. `whisper-base.en`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub english_variant: Option<String>,
+    /// False for checkpoints that were not trained to translate, such as large-v3-turbo.
+    #[serde(default = "default_true")]
+    pub translates: bool,
+}
+
+impl ModelEntry {
+    /// Compares a downloaded file's size and digest with the registry, where the registry knows them.
+    pub fn check(&self, size: u64, sha256: Option<&str>) -> Result<(), String> {
+        if let Some(expected) = self.size {
+            if size != expected {
+                return Err(format!("{} is {} bytes, expected {}", self.file_name, size, expected));
+            }
+        }
+        if let (Some(expected), Some(actual)) = (&self.sha256, sha256) {
+            if !expected.eq_ignore_ascii_case(actual) {
+                return Err(format!("{} has SHA-256 {}, expected {}", self.file_name, actual, expected));
+            }
+        }
+        Ok(())
+    }
+
+    /// Size check only, cheap enough to run every time a cached model is used.
+    pub fn check_size(&self, path: &Path) -> Result<(), String> {
+        let size = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+        self.check(size, None)
+    }
+
+    /// Cheap check run every time a cached model is used: the ggml magic number plus the
+    /// size the registry expects or, where it has none, the size recorded when the file
+    /// was verified. A file neither of them vouches for is rejected.
+    pub fn check_cached(&self, path: &Path) -> Result<(), String> {
+        check_magic(path)?;
+        if self.size.is_some() {
+            return self.check_size(path);
+        }
+        let verified = read_verified(path)
+            .ok_or_else(|| format!("{} has no recorded size to check it against", self.file_name))?;
+        let size = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+        if size != verified.size {
+            return Err(format!("{} is {} bytes, expected {}", self.file_name, size, verified.size));
+        }
+        Ok(())
+    }
+
+    /// Checks a finished download in `part_path` against the registry's SHA-256 and renames
+    /// it to `model_path`; a file that fails is deleted. The server's own `Content-Length`
+    /// proves nothing about the content, so a model without a pinned checksum is refused.
+    pub fn install_download(&self, part_path: &Path, model_path: &Path, downloaded: &Downloaded) -> Result<(), String> {
+        let verified = (|| {
+            self.require_checksum()?;
+            self.check(downloaded.size, Some(&downloaded.sha256))?;
+            check_magic(part_path)
+        })();
+        if let Err(e) = verified {
+            discard_partial(part_path);
+            return Err(e);
+        }
+
+        fs::rename(part_path, model_path).map_err(|e| format!("Failed to install {}: {}", self.file_name, e))?;
+        self.record_verified(model_path, downloaded.size, &downloaded.sha256)
+    }
+
+    /// Fails for models that cannot be downloaded because nothing pins their content.
+    pub fn require_checksum(&self) -> Result<(), String> {
+        match self.sha256 {
+            Some(_) => Ok(()),
+            None => Err(format!(
+                "the registry has no SHA-256 for {}, pin it with `srtify models pin` first",
+                self.name
+            )),
+        }
+    }
+
+    /// Keeps the size and SHA-256 a model was verified with next to it, unless the registry
+    /// already pins both.
+    pub fn record_verified(&self, model_path: &Path, size: u64, sha256: &str) -> Result<(), String> {
+        if self.size.is_some() && self.sha256.is_some() {
+            return Ok(());
+        }
+        let path = verified_path(model_path);
+        let json = serde_json::to_string(&VerifiedModel { size, sha256: sha256.to_string() }).map_err(|e| e.to_string())?;
+        fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Hashes the whole file and checks it, returning the SHA-256 so unknown checksums can be pinned.
+    pub fn verify_file(&self, path: &Path) -> Result<String, String> {
+        let size = fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+        let sha256 = sha256_file(path)?;
+        self.check(size, Some(&sha256))?;
+        Ok(sha256)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Model entry without a name".to_string());
+        }
+        if !is_plain_file_name(&self.file_name) {
+            return Err(format!("Invalid file name for model {}: {}", self.name, self.file_name));
+        }
+        if !self.url.starts_with("https://") {
+            return Err(format!("Model {} must be downloaded over https: {}", self.name, self.url));
+        }
+        if let Some(sha256) = &self.sha256 {
+            if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Invalid SHA-256 for model {}: {}", self.name, sha256));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Known models: the bundled manifest with the user's overrides applied on top.
+#[derive(Debug, Clone)]
+pub struct ModelRegistry {
+    models: Vec<ModelEntry>,
+}
+
+impl ModelRegistry {
+    /// Loads the bundled manifest and merges the overrides file from the app data dir into it.
+    pub fn load(app: &AppHandle) -> Result<ModelRegistry, String> {
+        ModelRegistry::with_overrides(&overrides_path(app)?)
+    }
+
+    /// Loads the bundled manifest and merges `overrides` into it, when that file exists.
+    ///
+    /// An override entry replaces the fields it names on the bundled entry of the same
+    /// name (so `{"name": "whisper-base", "sha256": "..."}` just pins a checksum), and
+    /// entries with new names are added.
+    pub fn with_overrides(overrides: &Path) -> Result<ModelRegistry, String> {
+        let mut models = manifest_models(BUNDLED_MANIFEST, "bundled model manifest")?;
+
+        if overrides.exists() {
+            let content = fs::read_to_string(overrides)
+                .map_err(|e| format!("Failed to read {}: {}", overrides.display(), e))?;
+            for entry in manifest_models(&content, &overrides.display().to_string())? {
+                let name = entry.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+                match models.iter_mut().find(|model| model["name"] == name.as_str()) {
+                    Some(Value::Object(model)) => {
+                        if let Value::Object(fields) = entry {
+                            model.extend(fields);
+                        }
+                    }
+                    _ => models.push(entry),
+                }
+            }
+        }
+
+        let models: Vec<ModelEntry> = models
+            .into_iter()
+            .map(|model| serde_json::from_value(model).map_err(|e| format!("Invalid model entry: {}", e)))
+            .collect::<Result<_, _>>()?;
+        for (i, model) in models.iter().enumerate() {
+            model.validate()?;
+            if models[..i].iter().any(|other| other.name == model.name) {
+                return Err(format!("Model {} is listed twice", model.name));
+            }
+        }
+
+        Ok(ModelRegistry { models })
+    }
+
+    pub fn entries(&self) -> &[ModelEntry] {
+        &self.models
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ModelEntry> {
+        self.models.iter().find(|model| model.name == name)
+    }
+
+    /// Picks the checkpoint to run for a model name, or `None` when the name is not in
+    /// the registry (and so is taken to be the path of a local model file).
+    ///
+    /// English transcription switches to the model's English-only variant where there is one.
+    pub fn resolve(&self, name: &str, language: &str, task: &str) -> Result<Option<&ModelEntry>, String> {
+        let Some(entry) = self.get(name) else {
+            return Ok(None);
+        };
+
+        if language == "en" && task == "transcribe" {
+            if let Some(variant) = entry.english_variant.as_deref().and_then(|variant| self.get(variant)) {
+                return Ok(Some(variant));
+            }
+        }
+        if !entry.multilingual && (language != "en" || task == "translate") {
+            return Err(format!(
+                "{} only understands English, pick a multilingual model for other languages or translation",
+                entry.name
+            ));
+        }
+        Ok(Some(entry))
+    }
+}
+
+/// Lists the registry so the UI can offer every known model, including ones added by overrides.
+#[tauri::command]
+pub fn list_registry_models(app: AppHandle) -> Result<Vec<ModelEntry>, String> {
+    ModelRegistry::load(&app).map(|registry| registry.entries().to_vec())
+}
+
+/// Outcome of re-verifying one cached model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelCheck {
+    pub name: String,
+    pub path: String,
+    /// SHA-256 of the file as found on disk.
+    pub sha256: Option<String>,
+    /// Why the file was rejected and deleted, `None` when it is intact.
+    pub error: Option<String>,
+}
+
+/// Re-hashes cached models (all of them, or just `name`) and deletes any that fail their size or checksum.
+#[tauri::command]
+pub async fn verify_models(app: AppHandle, name: Option<String>) -> Result<Vec<ModelCheck>, String> {
+    let registry = ModelRegistry::load(&app)?;
+    let entries: Vec<ModelEntry> = match &name {
+        Some(name) => vec![registry.get(name).cloned().ok_or_else(|| format!("Unknown model: {}", name))?],
+        None => registry.entries().to_vec(),
+    };
+
+    let dir = models_dir(&app)?;
+    let mut checks = Vec::new();
+    for entry in entries {
+        let path = dir.join(&entry.file_name);
+        if !path.exists() {
+            if name.is_some() {
+                return Err(format!("{} has not been downloaded", entry.name));
+            }
+            continue;
+        }
+
+        app.emit("info", format!("Verifying {}", entry.name)).unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+        let (file, checked) = (path.clone(), entry.clone());
+        let result = tokio::task::spawn_blocking(move || checked.verify_file(&file))
+            .await
+            .map_err(|e| format!("Verification of {} failed: {}", entry.name, e))?;
+
+        let check = match result {
+            Ok(sha256) => ModelCheck {
+                name: entry.name.clone(),
+                path: path.to_string_lossy().to_string(),
+                sha256: Some(sha256),
+                error: None,
+            },
+            Err(e) => {
+                fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+                app.emit("error", format!("Deleted corrupt model {}: {}", entry.name, e)).unwrap_or_else(|e| {
+                    eprintln!("Emit error: {}", e);
+                });
+                ModelCheck {
+                    name: entry.name.clone(),
+                    path: path.to_string_lossy().to_string(),
+                    sha256: None,
+                    error: Some(e),
+                }
+            }
+        };
+        checks.push(check);
+    }
+    Ok(checks)
+}
+
+/// Size and SHA-256 of a model as verified on download or import, kept as
+/// `<model>.verified.json` for models the registry has no size or checksum for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct VerifiedModel {
+    size: u64,
+    sha256: String,
+}
+
+/// Path of the file recording how a model was verified.
+pub fn verified_path(model_path: &Path) -> PathBuf {
+    let mut path = model_path.as_os_str().to_owned();
+    path.push(".verified.json");
+    PathBuf::from(path)
+}
+
+fn read_verified(model_path: &Path) -> Option<VerifiedModel> {
+    let content = fs::read_to_string(verified_path(model_path)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Rejects files that do not start with the ggml magic number, e.g. PyTorch checkpoints
+/// or HTML error pages.
+pub fn check_magic(path: &Path) -> Result<(), String> {
+    let mut magic = [0; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if &magic != GGML_MAGIC {
+        return Err(format!("{} is not a whisper.cpp (ggml) model", path.display()));
+    }
+    Ok(())
+}
+
+/// Where a model is downloaded to before it has been verified and renamed into place.
+pub fn partial_path(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".part");
+    PathBuf::from(partial)
+}
+
+/// A bare file name that, joined onto the model cache, cannot climb out of it.
+pub fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.')
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// User-editable manifest merged over the bundled one, kept in the app data dir next to
+/// the model cache so the OS does not clean it up along with the temp folder.
+pub fn overrides_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("models.json"))
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))
+}
+
+/// Moves an overrides file written by older versions into the temp folder over to
+/// [`overrides_path`], unless one is already there. Runs once at startup.
+pub fn migrate_legacy_overrides(app: &AppHandle) {
+    let legacy = env::temp_dir().join("srtify").join("models.json");
+    let Ok(target) = overrides_path(app) else {
+        return;
+    };
+    if !legacy.is_file() || target.exists() {
+        return;
+    }
+    // The temp dir is often on another file system, where only a copy will do
+    let moved = (|| {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&legacy, &target).or_else(|_| fs::copy(&legacy, &target).and_then(|_| fs::remove_file(&legacy)))
+    })();
+    if let Err(e) = moved {
+        eprintln!("Failed to move {} to {}: {}", legacy.display(), target.display(), e);
+    }
+}
+
+/// `srtify models pin <manifest>`: fills in the exact size and SHA-256 of every entry from
+/// the download server and rewrites the manifest in place. Returns the process exit code.
+pub async fn run_cli(args: &[String]) -> i32 {
+    match args {
+        [command, manifest] if command == "pin" => match pin_manifest(Path::new(manifest)).await {
+            Ok(pinned) => {
+                for line in pinned {
+                    println!("{}", line);
+                }
+                0
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                1
+            }
+        },
+        [help] if help == "-h" || help == "--help" => {
+            println!("{}", MODELS_CLI_USAGE);
+            0
+        }
+        _ => {
+            eprintln!("{}", MODELS_CLI_USAGE);
+            2
+        }
+    }
+}
+
+const MODELS_CLI_USAGE: &str = "Usage: srtify models pin <manifest.json>";
+
+/// Looks up every entry's size and SHA-256 and writes them into the manifest, returning
+/// one `name size sha256` line per model.
+async fn pin_manifest(path: &Path) -> Result<Vec<String>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let origin = path.display().to_string();
+    // Hugging Face answers with a redirect to its CDN that carries the LFS metadata
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut pins = HashMap::new();
+    let mut report = Vec::new();
+    for entry in manifest_models(&content, &origin)? {
+        let name = entry["name"].as_str().unwrap_or_default().to_string();
+        let url = entry["url"].as_str().ok_or_else(|| format!("Model {} has no url", name))?.to_string();
+        let (size, sha256) = linked_file(&client, &url).await.map_err(|e| format!("{}: {}", name, e))?;
+        report.push(format!("{} {} {}", name, size, sha256));
+        pins.insert(url, (size, sha256));
+    }
+
+    let pinned = pin_lines(&content, &pins);
+    for model in manifest_models(&pinned, &origin)? {
+        let entry: ModelEntry = serde_json::from_value(model).map_err(|e| format!("Invalid model entry: {}", e))?;
+        entry.validate()?;
+        if entry.size.is_none() || entry.sha256.is_none() {
+            return Err(format!("Could not pin {}, {} must list one field per line", entry.name, origin));
+        }
+    }
+    fs::write(path, pinned).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(report)
+}
+
+/// Size and SHA-256 of a Git LFS file, from the `X-Linked-Size` and `X-Linked-Etag`
+/// headers Hugging Face sends with its download redirect.
+async fn linked_file(client: &reqwest::Client, url: &str) -> Result<(u64, String), String> {
+    let response = client.head(url).send().await.map_err(|e| format!("Failed to reach {}: {}", url, e))?;
+    let header = |name: &str| response.headers().get(name).and_then(|value| value.to_str().ok());
+    let size = header("x-linked-size").and_then(|size| size.parse().ok());
+    let sha256 = header("x-linked-etag")
+        .map(|etag| etag.trim_start_matches("W/").trim_matches('"').to_lowercase())
+        .filter(|etag| etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit()));
+    match (size, sha256) {
+        (Some(size), Some(sha256)) => Ok((size, sha256)),
+        _ => Err(format!("{} did not report an LFS size and SHA-256 (HTTP {})", url, response.status())),
+    }
+}
+
+/// Rewrites the `size` and `sha256` lines after each entry's `url` line, leaving the rest
+/// of the manifest's layout alone.
+fn pin_lines(content: &str, pins: &HashMap<String, (u64, String)>) -> String {
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        let key = line.trim_start().split(':').next().unwrap_or_default();
+        if key == "\"size\"" || key == "\"sha256\"" {
+            continue;
+        }
+        let url = (key == "\"url\"")
+            .then(|| line.trim().trim_start_matches("\"url\":").trim().trim_end_matches(','))
+            .and_then(|value| serde_json::from_str::<String>(value).ok());
+        let Some((size, sha256)) = url.and_then(|url| pins.get(&url)) else {
+            lines.push(line.to_string());
+            continue;
+        };
+
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let last_field = !line.trim_end().ends_with(',');
+        lines.push(if last_field { format!("{},", line.trim_end()) } else { line.to_string() });
+        lines.push(format!("{}\"size\": {},", indent, size));
+        lines.push(format!("{}\"sha256\": \"{}\"{}", indent, sha256, if last_field { "" } else { "," }));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Returns the raw `models` array of a manifest so overrides can be merged field by field.
+fn manifest_models(content: &str, origin: &str) -> Result<Vec<Value>, String> {
+    let manifest: Value = serde_json::from_str(content).map_err(|e| format!("Invalid {}: {}", origin, e))?;
+    match manifest.get("models") {
+        Some(Value::Array(models)) if models.iter().all(Value::is_object) => Ok(models.clone()),
+        _ => Err(format!("Invalid {}: expected a \"models\" array of objects", origin)),
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use axum::routing::head;
+    use axum::Router;
+
+    const SHA: &str = "a3b1c2d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8f90";
+
+    fn scratch_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("srtify-models-test-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn overrides_pin_fields_and_add_models() {
+        let overrides = scratch_file(
+            "overrides.json",
+            &format!(
+                r#"{{ "models": [
+                    {{ "name": "whisper-base", "size": 42, "sha256": "{}" }},
+                    {{ "name": "custom", "file_name": "ggml-custom.bin", "url": "https://example.com/ggml-custom.bin",
+                       "multilingual": false }}
+                ] }}"#,
+                SHA
+            ),
+        );
+        let registry = ModelRegistry::with_overrides(&overrides).unwrap();
+        let base = registry.get("whisper-base").unwrap();
+        assert_eq!((base.size, base.sha256.as_deref()), (Some(42), Some(SHA)));
+        assert_eq!(base.file_name, "ggml-base.bin");
+        assert!(registry.get("custom").is_some_and(|custom| !custom.multilingual && custom.translates));
+        fs::remove_file(&overrides).ok();
+
+        let missing = env::temp_dir().join("srtify-models-test-missing.json");
+        assert!(ModelRegistry::with_overrides(&missing).unwrap().get("custom").is_none());
+    }
+
+    // Ignored until `srtify models pin src-tauri/models.json` has been run with network access;
+    // every download is refused while the registry has no checksum for it
+    #[test]
+    #[ignore = "models.json has not been pinned yet"]
+    fn bundled_manifest_pins_every_model() {
+        let registry = ModelRegistry::with_overrides(Path::new("")).unwrap();
+        for entry in registry.entries() {
+            assert!(entry.size.is_some_and(|size| size > 0), "{} has no size", entry.name);
+            let sha256 = entry.sha256.as_deref().unwrap_or_default();
+            assert!(
+                sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()),
+                "{} has no SHA-256",
+                entry.name
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        for (name, content) in [
+            ("bad-sha.json", r#"{ "models": [{ "name": "whisper-base", "sha256": "abc" }] }"#),
+            ("bad-path.json", r#"{ "models": [{ "name": "whisper-base", "file_name": "../evil.bin" }] }"#),
+            ("bad-url.json", r#"{ "models": [{ "name": "whisper-base", "url": "http://example.com/a.bin" }] }"#),
+            ("bad-shape.json", r#"{ "models": {} }"#),
+        ] {
+            let overrides = scratch_file(name, content);
+            assert!(ModelRegistry::with_overrides(&overrides).is_err(), "{}", name);
+            fs::remove_file(&overrides).ok();
+        }
+    }
+
+    #[test]
+    fn english_transcription_uses_the_english_variant() {
+        let registry = ModelRegistry::with_overrides(Path::new("")).unwrap();
+        assert_eq!(registry.resolve("whisper-base", "en", "transcribe").unwrap().unwrap().name, "whisper-base.en");
+        assert_eq!(registry.resolve("whisper-base", "de", "transcribe").unwrap().unwrap().name, "whisper-base");
+        assert_eq!(registry.resolve("whisper-base", "en", "translate").unwrap().unwrap().name, "whisper-base");
+        assert!(registry.resolve("whisper-base.en", "de", "transcribe").is_err());
+        assert!(registry.resolve("/models/mine.bin", "en", "transcribe").unwrap().is_none());
+    }
+
+    #[test]
+    fn check_compares_size_and_checksum() {
+        let entry = ModelEntry { size: Some(10), sha256: Some(SHA.to_uppercase()), ..registry_entry() };
+        assert!(entry.check(10, Some(SHA)).is_ok());
+        assert!(entry.check(10, None).is_ok());
+        assert!(entry.check(9, Some(SHA)).is_err());
+        assert!(entry.check(10, Some(&"0".repeat(64))).is_err());
+    }
+
+    /// A `.part` file holding a tiny "model" and what the download reported for it.
+    fn finished_download(name: &str, content: &[u8]) -> (PathBuf, Downloaded) {
+        let part_path = scratch_file(name, "");
+        fs::write(&part_path, content).unwrap();
+        let downloaded = Downloaded {
+            size: content.len() as u64,
+            sha256: sha256_file(&part_path).unwrap(),
+        };
+        (part_path, downloaded)
+    }
+
+    #[test]
+    fn verified_download_is_renamed_and_recorded() {
+        let (part_path, downloaded) = finished_download("ok.bin.part", b"lmggweights");
+        let model_path = part_path.with_extension("");
+        // Pinned checksum but no size: the size is recorded for the cheap check on later use
+        let entry = ModelEntry { sha256: Some(downloaded.sha256.clone()), ..registry_entry() };
+        entry.install_download(&part_path, &model_path, &downloaded).unwrap();
+
+        assert!(!part_path.exists());
+        assert!(entry.check_cached(&model_path).is_ok());
+        let verified = read_verified(&model_path).unwrap();
+        assert_eq!((verified.size, verified.sha256), (downloaded.size, downloaded.sha256));
+
+        // A cached copy that lost bytes no longer matches the recorded size
+        fs::write(&model_path, b"lmggweigh").unwrap();
+        assert!(entry.check_cached(&model_path).is_err());
+        fs::remove_file(&model_path).ok();
+        fs::remove_file(verified_path(&model_path)).ok();
+    }
+
+    #[test]
+    fn rejected_downloads_are_deleted() {
+        let pinned = |content: &[u8]| {
+            let sha256 = format!("{:x}", Sha256::digest(content));
+            ModelEntry { size: Some(content.len() as u64), sha256: Some(sha256), ..registry_entry() }
+        };
+        for (name, content, entry) in [
+            ("html.bin.part", &b"<html>oops</html>"[..], pinned(b"<html>oops</html>")),
+            ("short.bin.part", &b"lmggweight"[..], pinned(b"lmggweights")),
+            ("corrupt.bin.part", &b"lmggweighty"[..], pinned(b"lmggweights")),
+            // The server's Content-Length matches, but nothing vouches for the content
+            ("unpinned.bin.part", &b"lmggweights"[..], registry_entry()),
+        ] {
+            let (part_path, downloaded) = finished_download(name, content);
+            let model_path = part_path.with_extension("");
+            assert!(entry.install_download(&part_path, &model_path, &downloaded).is_err(), "{}", name);
+            assert!(!part_path.exists() && !model_path.exists(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn cached_models_need_the_magic_and_a_known_size() {
+        let model_path = scratch_file("cached.bin", "lmggweights");
+        assert!(registry_entry().check_cached(&model_path).is_err());
+        assert!(ModelEntry { size: Some(11), ..registry_entry() }.check_cached(&model_path).is_ok());
+        assert!(ModelEntry { size: Some(12), ..registry_entry() }.check_cached(&model_path).is_err());
+        fs::write(&model_path, "ggmlweights").unwrap();
+        assert!(ModelEntry { size: Some(11), ..registry_entry() }.check_cached(&model_path).is_err());
+        fs::remove_file(&model_path).ok();
+    }
+
+    /// A bundled entry with nothing pinned, whatever `models.json` says.
+    fn registry_entry() -> ModelEntry {
+        let entry = ModelRegistry::with_overrides(Path::new("")).unwrap().get("whisper-tiny").unwrap().clone();
+        ModelEntry { size: None, sha256: None, ..entry }
+    }
+
+    #[test]
+    fn pin_lines_rewrites_size_and_checksum_after_the_url() {
+        let content = "{\n  \"models\": [\n    {\n      \"name\": \"a\",\n      \"url\": \"https://example.com/a.bin\",\n      \
+                       \"size\": 1,\n      \"multilingual\": true\n    },\n    {\n      \"name\": \"b\",\n      \
+                       \"url\": \"https://example.com/b.bin\"\n    }\n  ]\n}\n";
+        let pins = HashMap::from([
+            ("https://example.com/a.bin".to_string(), (100, SHA.to_string())),
+            ("https://example.com/b.bin".to_string(), (200, SHA.to_string())),
+        ]);
+        let pinned = pin_lines(content, &pins);
+        let models = manifest_models(&pinned, "pinned").unwrap();
+        assert_eq!(models[0]["size"], 100);
+        assert_eq!(models[0]["sha256"], SHA);
+        assert_eq!(models[0]["multilingual"], true);
+        assert_eq!(models[1]["size"], 200);
+        assert!(pinned.contains("      \"url\": \"https://example.com/a.bin\",\n      \"size\": 100,\n"));
+    }
+
+    #[tokio::test]
+    async fn linked_file_reads_the_lfs_headers_of_the_redirect() {
+        let redirect = || async {
+            let mut headers = HeaderMap::new();
+            headers.insert("location", HeaderValue::from_static("https://cdn.example.com/blob"));
+            headers.insert("x-linked-size", HeaderValue::from_static("77691713"));
+            headers.insert("x-linked-etag", HeaderValue::from_str(&format!("\"{}\"", SHA)).unwrap());
+            (StatusCode::FOUND, headers)
+        };
+        let base = serve(Router::new().route("/lfs.bin", head(redirect)).route("/plain.bin", head(|| async { "" }))).await;
+        let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+
+        assert_eq!(linked_file(&client, &format!("{}/lfs.bin", base)).await, Ok((77_691_713, SHA.to_string())));
+        assert!(linked_file(&client, &format!("{}/plain.bin", base)).await.is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter};
+
+use crate::text_export::TranscriptFormat;
+use crate::ttml::TtmlProfile;
+
+/// Template used when the user has not configured one.
+pub const DEFAULT_NAMING_TEMPLATE: &str = "{stem}";
+
+const TEMPLATE_TOKENS: [&str; 3] = ["{stem}", "{lang}", "{model}"];
+
+/// Every output format a job can write.
+pub const OUTPUT_FORMATS: [&str; 11] = [
+    "srt", "json", "vtt", "ass", "ttml", "ebu-tt-d", "txt", "md", "csv", "tsv", "lrc",
+];
+
+/// Formats written when the user has not picked any.
+pub const DEFAULT_OUTPUT_FORMATS: &str = "srt,json";
+
+/// Parses a comma separated format list such as `srt,vtt`.
+pub fn parse_output_formats(value: &str) -> Result<Vec<String>, String> {
+    let mut formats = Vec::new();
+    for format in value.split(',').map(|f| f.trim().to_lowercase()).filter(|f| !f.is_empty()) {
+        if !OUTPUT_FORMATS.contains(&format.as_str()) {
+            return Err(format!("Unknown output format: {}", format));
+        }
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+
+    if formats.is_empty() {
+        return Err("Select at least one output format".to_string());
+    }
+    Ok(formats)
+}
+
+/// What to do when an output file with the rendered name already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    Overwrite,
+    Suffix,
+    Skip,
+}
+
+impl CollisionPolicy {
+    pub fn parse(value: &str) -> Option<CollisionPolicy> {
+        match value {
+            "overwrite" => Some(CollisionPolicy::Overwrite),
+            "suffix" => Some(CollisionPolicy::Suffix),
+            "skip" => Some(CollisionPolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// Checks that a naming template only uses the supported `{stem}`, `{lang}` and `{model}` tokens.
+pub fn validate_naming_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed token in naming template: {}", template))?;
+        let token = &rest[open..open + close + 1];
+        if !TEMPLATE_TOKENS.contains(&token) {
+            return Err(format!(
+                "Unknown token {} in naming template, expected one of {}",
+                token,
+                TEMPLATE_TOKENS.join(", ")
+            ));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    Ok(())
+}
+
+/// File extension an output format is written with, e.g. `words.json` for `json`.
+pub fn output_extension(format: &str) -> Option<&'static str> {
+    match format {
+        "srt" => Some("srt"),
+        "vtt" => Some("vtt"),
+        "ass" => Some("ass"),
+        "lrc" => Some("lrc"),
+        "json" => Some("words.json"),
+        "ttml" => Some(TtmlProfile::Imsc1.extension()),
+        "ebu-tt-d" => Some(TtmlProfile::EbuTtD.extension()),
+        other => TranscriptFormat::parse(other).map(TranscriptFormat::extension),
+    }
+}
+
+/// Resolves where each output of a job is written.
+///
+/// The template is rendered once into a base name; every writer then appends its
+/// own extension (`interview.srt`, `interview.words.json`, ...). With the suffix
+/// policy one suffix is picked for the whole job, so the outputs of a run keep
+/// sharing a base name (`interview-1.srt` next to `interview-1.words.json`).
+#[derive(Debug, Clone)]
+pub struct OutputNaming {
+    folder: PathBuf,
+    base_name: String,
+    collision: CollisionPolicy,
+}
+
+impl OutputNaming {
+    pub fn new(
+        folder: &str,
+        template: &str,
+        media_file: &str,
+        language: &str,
+        model: &str,
+        collision: CollisionPolicy,
+        extensions: &[&str],
+    ) -> OutputNaming {
+        let stem = file_stem(media_file);
+        let model = model_label(model);
+
+        let rendered = template
+            .replace("{stem}", &stem)
+            .replace("{lang}", language)
+            .replace("{model}", &model);
+        let base_name = sanitize_file_name(&rendered);
+        let base_name = if base_name.is_empty() { "output".to_string() } else { base_name };
+        let folder = PathBuf::from(folder);
+
+        let taken = |base: &str| extensions.iter().any(|ext| folder.join(format!("{}.{}", base, ext)).exists());
+        let base_name = if collision == CollisionPolicy::Suffix && taken(&base_name) {
+            (1..)
+                .map(|n| format!("{}-{}", base_name, n))
+                .find(|candidate| !taken(candidate))
+                .unwrap_or(base_name)
+        } else {
+            base_name
+        };
+
+        OutputNaming {
+            folder,
+            base_name,
+            collision,
+        }
+    }
+
+    /// Returns the path to write an output with `extension` to, or `None` when the
+    /// file already exists and the collision policy says to leave it alone.
+    pub fn resolve(&self, extension: &str) -> Option<PathBuf> {
+        let path = self.folder.join(format!("{}.{}", self.base_name, extension));
+        if !path.exists() {
+            return Some(path);
+        }
+
+        match self.collision {
+            CollisionPolicy::Overwrite => Some(path),
+            CollisionPolicy::Skip => None,
+            // The job's suffix was free when it was picked; only a file created since lands here
+            CollisionPolicy::Suffix => (1..)
+                .map(|n| self.folder.join(format!("{}-{}.{}", self.base_name, n, extension)))
+                .find(|candidate| !candidate.exists()),
+        }
+    }
+}
+
+/// Resolves an output path and tells the frontend when an existing file is kept.
+pub fn prepare_output(naming: &OutputNaming, extension: &str, app: &AppHandle) -> Option<PathBuf> {
+    let path = naming.resolve(extension);
+    if path.is_none() {
+        app.emit(
+            "info",
+            format!("Skipped {}.{}: file already exists", naming.base_name, extension),
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+    }
+    path
+}
+
+fn file_stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Registry names such as `whisper-small.en` are used as is. Custom models are selected
+/// by path, of which only the file name without `.bin` is useful in an output name.
+fn model_label(model: &str) -> String {
+    match Path::new(model).file_name() {
+        Some(name) if name != model => {
+            let name = name.to_string_lossy();
+            name.strip_suffix(".bin").unwrap_or(&name).to_string()
+        }
+        _ => model.to_string(),
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn scratch_folder(name: &str) -> PathBuf {
+        let folder = std::env::temp_dir().join(format!("srtify-output-test-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&folder).ok();
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn naming(folder: &Path, collision: CollisionPolicy) -> OutputNaming {
+        OutputNaming::new(
+            &folder.to_string_lossy(),
+            DEFAULT_NAMING_TEMPLATE,
+            "/media/interview.mp4",
+            "en",
+            "whisper-base",
+            collision,
+            &["srt", "words.json"],
+        )
+    }
+
+    #[test]
+    fn suffix_is_shared_by_every_output_of_a_job() {
+        let folder = scratch_folder("shared");
+        // Only the JSON name is taken, the SRT must still move along with it
+        fs::write(folder.join("interview.words.json"), "{}").unwrap();
+
+        let naming = naming(&folder, CollisionPolicy::Suffix);
+        assert_eq!(naming.resolve("srt"), Some(folder.join("interview-1.srt")));
+        assert_eq!(naming.resolve("words.json"), Some(folder.join("interview-1.words.json")));
+        fs::remove_dir_all(&folder).ok();
+    }
+
+    #[test]
+    fn suffix_skips_numbers_taken_by_any_format() {
+        let folder = scratch_folder("taken");
+        fs::write(folder.join("interview.srt"), "").unwrap();
+        fs::write(folder.join("interview-1.words.json"), "{}").unwrap();
+
+        let naming = naming(&folder, CollisionPolicy::Suffix);
+        assert_eq!(naming.resolve("srt"), Some(folder.join("interview-2.srt")));
+        assert_eq!(naming.resolve("words.json"), Some(folder.join("interview-2.words.json")));
+        fs::remove_dir_all(&folder).ok();
+    }
+
+    #[test]
+    fn free_names_and_other_policies_keep_the_base_name() {
+        let folder = scratch_folder("policies");
+        assert_eq!(naming(&folder, CollisionPolicy::Suffix).resolve("srt"), Some(folder.join("interview.srt")));
+
+        fs::write(folder.join("interview.srt"), "").unwrap();
+        assert_eq!(naming(&folder, CollisionPolicy::Overwrite).resolve("srt"), Some(folder.join("interview.srt")));
+        assert_eq!(naming(&folder, CollisionPolicy::Skip).resolve("srt"), None);
+        assert_eq!(
+            naming(&folder, CollisionPolicy::Skip).resolve("words.json"),
+            Some(folder.join("interview.words.json"))
+        );
+        fs::remove_dir_all(&folder).ok();
+    }
+
+    #[test]
+    fn model_names_are_kept_whole() {
+        let folder = scratch_folder("model");
+        let named = |model: &str| {
+            OutputNaming::new(
+                &folder.to_string_lossy(),
+                "{stem}.{model}",
+                "/media/interview.mp4",
+                "en",
+                model,
+                CollisionPolicy::Overwrite,
+                &["srt"],
+            )
+            .resolve("srt")
+        };
+        assert_eq!(named("whisper-small.en"), Some(folder.join("interview.whisper-small.en.srt")));
+        assert_eq!(named("/models/ggml-custom.en.bin"), Some(folder.join("interview.ggml-custom.en.srt")));
+        assert_eq!(named("my:model"), Some(folder.join("interview.my_model.srt")));
+        fs::remove_dir_all(&folder).ok();
+    }
+
+    #[test]
+    fn every_format_has_an_extension() {
+        for format in OUTPUT_FORMATS {
+            assert!(output_extension(format).is_some(), "{}", format);
+        }
+        assert_eq!(output_extension("json"), Some("words.json"));
+        assert_eq!(output_extension("ebu-tt-d"), Some("ebu-tt-d.xml"));
+    }
+}
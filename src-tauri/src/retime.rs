@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transcript::Segment;
+
+/// A time as it is now and where it should end up, both in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub from: f64,
+    pub to: f64,
+}
+
+/// Cue retiming settings, persisted as the `retime` selection.
+///
+/// The adjustments are applied in field order: frame-rate conversion, then the
+/// stretch between the two anchors, then the constant offset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetimeOptions {
+    /// Frame rate the subtitles were timed against, e.g. 23.976.
+    pub source_fps: Option<f64>,
+    /// Frame rate of the re-edited video, e.g. 25.
+    pub target_fps: Option<f64>,
+    /// Two points whose times are known before and after the edit (`from` measured
+    /// after any frame-rate conversion); everything else is scaled linearly so both
+    /// land where they should.
+    pub anchors: Option<[Anchor; 2]>,
+    /// Seconds added to every time; negative values pull cues earlier.
+    pub offset: f64,
+}
+
+impl RetimeOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.source_fps, self.target_fps) {
+            (None, None) => {}
+            (Some(source), Some(target)) if valid_fps(source) && valid_fps(target) => {}
+            (Some(_), Some(_)) => return Err("Frame rates must be greater than 0".to_string()),
+            _ => return Err("Frame-rate conversion needs both a source and a target frame rate".to_string()),
+        }
+        if let Some([first, second]) = self.anchors {
+            if [first.from, first.to, second.from, second.to].iter().any(|t| !t.is_finite() || *t < 0.0) {
+                return Err("Anchor times cannot be negative".to_string());
+            }
+            if (second.from - first.from).abs() < 0.001 || (second.to - first.to).abs() < 0.001 {
+                return Err("The two anchors must be at different times".to_string());
+            }
+            if (second.to - first.to) / (second.from - first.from) <= 0.0 {
+                return Err("Anchors cannot reverse the order of the cues".to_string());
+            }
+        }
+        if !self.offset.is_finite() {
+            return Err(format!("Invalid retiming offset: {}", self.offset));
+        }
+        Ok(())
+    }
+
+    /// True when applying these options would leave every time where it is.
+    pub fn is_identity(&self) -> bool {
+        let (scale, shift) = self.transform();
+        (scale - 1.0).abs() < f64::EPSILON && shift.abs() < 0.0005
+    }
+
+    /// Collapses the three adjustments into a single `t * scale + shift` mapping (seconds).
+    fn transform(&self) -> (f64, f64) {
+        let mut scale = 1.0;
+        let mut shift = self.offset;
+
+        // Played back at the target rate, the same frames go by faster (or slower)
+        if let (Some(source), Some(target)) = (self.source_fps, self.target_fps) {
+            scale = source / target;
+        }
+        if let Some([first, second]) = self.anchors {
+            let stretch = (second.to - first.to) / (second.from - first.from);
+            scale *= stretch;
+            shift += first.to - first.from * stretch;
+        }
+        (scale, shift)
+    }
+}
+
+/// Moves every cue and word by the configured adjustments and returns how many cues
+/// were dropped because they now end before zero. Cues that start before zero are clipped.
+///
+/// Run [`crate::timing::sanitize_cues`] afterwards; rounding can make neighbours touch.
+pub fn retime_segments(segments: &mut Vec<Segment>, options: &RetimeOptions) -> usize {
+    let (scale, shift) = options.transform();
+    let map = |ms: u64| -> i64 { ((ms as f64 / 1000.0 * scale + shift) * 1000.0).round() as i64 };
+
+    let before = segments.len();
+    segments.retain_mut(|segment| {
+        let end = map(segment.end_ms);
+        if end <= 0 {
+            return false;
+        }
+        segment.start_ms = map(segment.start_ms).max(0) as u64;
+        segment.end_ms = end as u64;
+        for word in &mut segment.words {
+            word.start_ms = map(word.start_ms).max(0) as u64;
+            word.end_ms = map(word.end_ms).max(0) as u64;
+        }
+        true
+    });
+    before - segments.len()
+}
+
+fn valid_fps(fps: f64) -> bool {
+    fps.is_finite() && fps > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::Word;
+
+    fn cue(start_ms: u64, end_ms: u64) -> Segment {
+        Segment { start_ms, end_ms, text: "text".to_string(), ..Default::default() }
+    }
+
+    fn retimed(segments: &[(u64, u64)], options: &RetimeOptions) -> (Vec<(u64, u64)>, usize) {
+        let mut segments: Vec<Segment> = segments.iter().map(|&(start, end)| cue(start, end)).collect();
+        let dropped = retime_segments(&mut segments, options);
+        (segments.iter().map(|s| (s.start_ms, s.end_ms)).collect(), dropped)
+    }
+
+    #[test]
+    fn offset_moves_cues_and_words() {
+        let mut segments = vec![Segment {
+            words: vec![Word { text: "text".to_string(), start_ms: 1200, end_ms: 1800, ..Default::default() }],
+            ..cue(1000, 2000)
+        }];
+        let options = RetimeOptions { offset: 1.5, ..Default::default() };
+        assert_eq!(retime_segments(&mut segments, &options), 0);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (2500, 3500));
+        assert_eq!((segments[0].words[0].start_ms, segments[0].words[0].end_ms), (2700, 3300));
+    }
+
+    #[test]
+    fn anchors_stretch_between_two_known_points() {
+        let options = RetimeOptions {
+            anchors: Some([Anchor { from: 10.0, to: 12.0 }, Anchor { from: 110.0, to: 117.0 }]),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+        let (segments, _) = retimed(&[(10_000, 60_000), (110_000, 111_000)], &options);
+        assert_eq!(segments, vec![(12_000, 64_500), (117_000, 118_050)]);
+    }
+
+    #[test]
+    fn converts_23_976_to_25_fps() {
+        let options = RetimeOptions { source_fps: Some(23.976), target_fps: Some(25.0), ..Default::default() };
+        let (segments, _) = retimed(&[(0, 1000), (100_000, 102_000), (3_600_000, 3_601_000)], &options);
+        assert_eq!(segments, vec![(0, 959), (95_904, 97_822), (3_452_544, 3_453_503)]);
+    }
+
+    #[test]
+    fn cues_moved_before_zero_are_dropped_or_clipped() {
+        let options = RetimeOptions { offset: -2.0, ..Default::default() };
+        let (segments, dropped) = retimed(&[(0, 1500), (1000, 2000), (1500, 3000), (5000, 6000)], &options);
+        assert_eq!(dropped, 2);
+        assert_eq!(segments, vec![(0, 1000), (3000, 4000)]);
+    }
+
+    #[test]
+    fn identity_and_invalid_options() {
+        assert!(RetimeOptions::default().is_identity());
+        assert!(RetimeOptions { source_fps: Some(25.0), target_fps: Some(25.0), ..Default::default() }.is_identity());
+        assert!(!RetimeOptions { offset: 0.001, ..Default::default() }.is_identity());
+
+        assert!(RetimeOptions { source_fps: Some(25.0), ..Default::default() }.validate().is_err());
+        assert!(RetimeOptions { source_fps: Some(0.0), target_fps: Some(25.0), ..Default::default() }.validate().is_err());
+        assert!(RetimeOptions { offset: f64::NAN, ..Default::default() }.validate().is_err());
+        let reversed = RetimeOptions {
+            anchors: Some([Anchor { from: 10.0, to: 20.0 }, Anchor { from: 20.0, to: 10.0 }]),
+            ..Default::default()
+        };
+        assert!(reversed.validate().is_err());
+        let same_time = RetimeOptions {
+            anchors: Some([Anchor { from: 10.0, to: 20.0 }, Anchor { from: 10.0, to: 30.0 }]),
+            ..Default::default()
+        };
+        assert!(same_time.validate().is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use serde::{Deserialize, Serialize};
+
+use crate::timing::sec_to_ms;
+use crate::transcript::{Segment, Transcript, Word};
+
+/// Limits a subtitle cue has to respect to stay readable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadabilityRules {
+    pub max_chars_per_line: usize,
+    pub max_lines: usize,
+    /// Seconds.
+    pub min_duration: f64,
+    /// Seconds.
+    pub max_duration: f64,
+    /// Reading speed limit in characters per second, spaces included.
+    pub max_cps: f64,
+}
+
+/// Named rule sets; `Off` writes whisper's segments verbatim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapingPreset {
+    Off,
+    Netflix,
+    Broadcast,
+    Social,
+}
+
+impl ShapingPreset {
+    pub fn parse(value: &str) -> Option<ShapingPreset> {
+        match value {
+            "off" => Some(ShapingPreset::Off),
+            "netflix" => Some(ShapingPreset::Netflix),
+            "broadcast" => Some(ShapingPreset::Broadcast),
+            "social" => Some(ShapingPreset::Social),
+            _ => None,
+        }
+    }
+
+    pub fn rules(self) -> Option<ReadabilityRules> {
+        match self {
+            ShapingPreset::Off => None,
+            // Netflix timed text style guide: 42 characters, 5/6 s to 7 s, 20 cps for adult content
+            ShapingPreset::Netflix => Some(ReadabilityRules {
+                max_chars_per_line: 42,
+                max_lines: 2,
+                min_duration: 0.833,
+                max_duration: 7.0,
+                max_cps: 20.0,
+            }),
+            // Teletext-era broadcast guidelines: 37 columns and a slower reading speed
+            ShapingPreset::Broadcast => Some(ReadabilityRules {
+                max_chars_per_line: 37,
+                max_lines: 2,
+                min_duration: 1.0,
+                max_duration: 6.0,
+                max_cps: 15.0,
+            }),
+            // Vertical video leaves little width, so show a few words at a time
+            ShapingPreset::Social => Some(ReadabilityRules {
+                max_chars_per_line: 20,
+                max_lines: 2,
+                min_duration: 0.5,
+                max_duration: 3.0,
+                max_cps: 22.0,
+            }),
+        }
+    }
+}
+
+/// Cue shaping settings, persisted as the `shaping` selection.
+///
+/// Any rule left unset comes from the preset. Shaping is off unless a preset is picked,
+/// so whisper's segments are written as they always were.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShapingOptions {
+    pub preset: String,
+    pub max_chars_per_line: Option<usize>,
+    pub max_lines: Option<usize>,
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub max_cps: Option<f64>,
+}
+
+impl Default for ShapingOptions {
+    fn default() -> Self {
+        ShapingOptions {
+            preset: "off".to_string(),
+            max_chars_per_line: None,
+            max_lines: None,
+            min_duration: None,
+            max_duration: None,
+            max_cps: None,
+        }
+    }
+}
+
+impl ShapingOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if ShapingPreset::parse(&self.preset).is_none() {
+            return Err(format!("Unknown subtitle preset: {}", self.preset));
+        }
+        if self.max_chars_per_line == Some(0) || self.max_lines == Some(0) {
+            return Err("Characters per line and lines per cue must be at least 1".to_string());
+        }
+        for (name, value) in [
+            ("minimum duration", self.min_duration),
+            ("maximum duration", self.max_duration),
+            ("characters per second", self.max_cps),
+        ] {
+            if value.is_some_and(|v| !v.is_finite() || v <= 0.0) {
+                return Err(format!("Subtitle {} must be a positive number", name));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if min > max {
+                return Err("Subtitle minimum duration is longer than the maximum".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// The preset's rules with any overrides applied, or `None` when shaping is off.
+    pub fn rules(&self) -> Option<ReadabilityRules> {
+        let preset = ShapingPreset::parse(&self.preset).unwrap_or(ShapingPreset::Off);
+        let base = preset.rules()?;
+        Some(ReadabilityRules {
+            max_chars_per_line: self.max_chars_per_line.unwrap_or(base.max_chars_per_line),
+            max_lines: self.max_lines.unwrap_or(base.max_lines),
+            min_duration: self.min_duration.unwrap_or(base.min_duration),
+            max_duration: self.max_duration.unwrap_or(base.max_duration).max(self.min_duration.unwrap_or(0.0)),
+            max_cps: self.max_cps.unwrap_or(base.max_cps),
+        })
+    }
+}
+
+/// Re-splits a transcript's segments into readable cues.
+///
+/// Long segments are cut at word boundaries (preferring punctuation) so every cue fits
+/// `max_lines` lines of `max_chars_per_line` and lasts at most `max_duration`; each cue's
+/// text is then wrapped into balanced lines joined by `\n`, and short or fast cues are
+/// extended towards `min_duration` / `max_cps` as far as the next cue allows. Cues the
+/// next one leaves too little room for still read too fast; see [`count_fast_cues`].
+pub fn shape_segments(transcript: &Transcript, rules: &ReadabilityRules) -> Vec<Segment> {
+    let mut cues: Vec<(Vec<Word>, &Segment)> = Vec::new();
+
+    for segment in transcript.cues() {
+        let words = if segment.words.is_empty() {
+            interpolate_words(segment)
+        } else {
+            segment.words.clone()
+        };
+
+        for chunk in split_words(&words, rules) {
+            cues.push((chunk, segment));
+        }
+    }
+
+    let min_duration = sec_to_ms(rules.min_duration);
+    let max_duration = sec_to_ms(rules.max_duration);
+    let mut shaped = Vec::with_capacity(cues.len());
+    for i in 0..cues.len() {
+        let (words, parent) = &cues[i];
+        let text = join_words(words);
+        let start = words.first().map(|w| w.start_ms).unwrap_or_default();
+        let natural_end = words.last().map(|w| w.end_ms).unwrap_or(start).max(start);
+        let next_start = cues.get(i + 1).and_then(|(next, _)| next.first()).map(|w| w.start_ms);
+
+        // Stretch towards the reading-speed and minimum-duration targets without overlapping the next cue
+        let wanted = sec_to_ms(text.chars().count() as f64 / rules.max_cps).max(min_duration);
+        let mut end = natural_end.max(start + wanted).min(start + max_duration.max(natural_end - start));
+        if let Some(next_start) = next_start {
+            end = end.min(next_start.max(natural_end));
+        }
+
+        shaped.push(Segment {
+            start_ms: start,
+            end_ms: end,
+            text: wrap_lines(&text, rules).join("\n"),
+            words: words.clone(),
+            // Style ranges point into the parent's text and do not survive re-wrapping
+            styles: Vec::new(),
+            ..(*parent).clone()
+        });
+    }
+
+    shaped
+}
+
+/// Counts cues that last too short to be read at `max_cps`, e.g. because the next cue
+/// starts right after them and left no room to stretch.
+pub fn count_fast_cues(segments: &[Segment], rules: &ReadabilityRules) -> usize {
+    segments
+        .iter()
+        .filter(|segment| {
+            let text = segment.text.trim();
+            !text.is_empty()
+                && segment.end_ms.saturating_sub(segment.start_ms) < sec_to_ms(text.chars().count() as f64 / rules.max_cps)
+        })
+        .count()
+}
+
+/// Spreads a segment's duration over its words by length when whisper gave no word timings.
+fn interpolate_words(segment: &Segment) -> Vec<Word> {
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let total_chars: usize = words.iter().map(|w| w.chars().count()).sum();
+    let duration = segment.end_ms.saturating_sub(segment.start_ms);
+
+    let mut chars_so_far = 0;
+    words
+        .into_iter()
+        .map(|word| {
+            let start_ms = segment.start_ms + duration * chars_so_far as u64 / total_chars.max(1) as u64;
+            chars_so_far += word.chars().count();
+            let end_ms = segment.start_ms + duration * chars_so_far as u64 / total_chars.max(1) as u64;
+            Word { text: word.to_string(), start_ms, end_ms, ..Default::default() }
+        })
+        .collect()
+}
+
+/// Greedily fills cues with words, backing up to the last punctuation mark in the second
+/// half of a full cue so sentences are not cut mid-clause.
+fn split_words(words: &[Word], rules: &ReadabilityRules) -> Vec<Vec<Word>> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<Word> = Vec::new();
+
+    for word in words {
+        let mut candidate = current.clone();
+        candidate.push(word.clone());
+
+        if current.is_empty() || fits(&candidate, rules) {
+            current = candidate;
+            continue;
+        }
+
+        let break_at = current
+            .iter()
+            .rposition(|w| ends_clause(&w.text))
+            .filter(|&i| i + 1 >= current.len().div_ceil(2) && i + 1 < current.len());
+        match break_at {
+            Some(i) => {
+                let rest = current.split_off(i + 1);
+                chunks.push(std::mem::replace(&mut current, rest));
+                current.push(word.clone());
+                // The carried-over words plus this one may still be too long on their own
+                if !fits(&current, rules) {
+                    let last = current.pop();
+                    chunks.push(std::mem::take(&mut current));
+                    current.extend(last);
+                }
+            }
+            None => {
+                chunks.push(std::mem::take(&mut current));
+                current.push(word.clone());
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn fits(words: &[Word], rules: &ReadabilityRules) -> bool {
+    let (Some(first), Some(last)) = (words.first(), words.last()) else {
+        return true;
+    };
+    let text = join_words(words);
+    last.end_ms.saturating_sub(first.start_ms) <= sec_to_ms(rules.max_duration) && wrap_lines(&text, rules).len() <= rules.max_lines
+}
+
+/// Wraps text into as few lines as possible, then evens their lengths out, preferring
+/// to break after punctuation. A single word longer than a line gets a line to itself.
+fn wrap_lines(text: &str, rules: &ReadabilityRules) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let width = rules.max_chars_per_line.max(1);
+    let total = text.chars().count();
+    if total <= width || words.len() < 2 {
+        return vec![words.join(" ")];
+    }
+
+    let line_count = greedy_wrap(&words, width).len();
+    // Try to reach the same line count with a narrower, balanced target width
+    let target = total.div_ceil(line_count);
+    let balanced = (target..=width)
+        .map(|w| greedy_wrap(&words, w))
+        .find(|lines| lines.len() == line_count)
+        .unwrap_or_else(|| greedy_wrap(&words, width));
+
+    if balanced.len() == 2 {
+        return best_two_line_split(&words, width).unwrap_or(balanced);
+    }
+    balanced
+}
+
+fn greedy_wrap(words: &[&str], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in words {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Picks the two-line break with the most even line lengths, giving breaks after
+/// punctuation a head start of a few characters.
+fn best_two_line_split(words: &[&str], width: usize) -> Option<Vec<String>> {
+    (1..words.len())
+        .filter_map(|i| {
+            let first = words[..i].join(" ");
+            let second = words[i..].join(" ");
+            let (a, b) = (first.chars().count(), second.chars().count());
+            if a > width || b > width {
+                return None;
+            }
+            let bonus = if ends_clause(words[i - 1]) { 6 } else { 0 };
+            Some((a.abs_diff(b) as i64 - bonus, vec![first, second]))
+        })
+        .min_by_key(|(score, _)| *score)
+        .map(|(_, lines)| lines)
+}
+
+fn ends_clause(word: &str) -> bool {
+    word.ends_with(['.', ',', '?', '!', ';', ':', '…'])
+}
+
+fn join_words(words: &[Word]) -> String {
+    words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NETFLIX: ReadabilityRules = ReadabilityRules {
+        max_chars_per_line: 42,
+        max_lines: 2,
+        min_duration: 0.833,
+        max_duration: 7.0,
+        max_cps: 20.0,
+    };
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment { start_ms, end_ms, text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn shaping_is_off_unless_a_preset_is_picked() {
+        let options = ShapingOptions::default();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.rules(), None);
+
+        let options = ShapingOptions { preset: "netflix".to_string(), max_cps: Some(17.0), ..Default::default() };
+        assert_eq!(options.rules(), Some(ReadabilityRules { max_cps: 17.0, ..NETFLIX }));
+    }
+
+    #[test]
+    fn long_segments_are_split_into_cues_that_fit() {
+        let text = "This is a rather long sentence, which goes on and on. It keeps going well past \
+                    what two lines of forty-two characters can hold on screen.";
+        let transcript = Transcript::new(vec![segment(0, 12_000, text)]);
+        let cues = shape_segments(&transcript, &NETFLIX);
+
+        assert!(cues.len() > 1);
+        let rejoined: Vec<&str> = cues.iter().flat_map(|cue| cue.text.split_whitespace()).collect();
+        assert_eq!(rejoined, text.split_whitespace().collect::<Vec<_>>());
+        for cue in &cues {
+            let lines: Vec<&str> = cue.text.lines().collect();
+            assert!(lines.len() <= 2, "{:?}", cue.text);
+            assert!(lines.iter().all(|line| line.chars().count() <= 42), "{:?}", cue.text);
+            assert!(cue.end_ms - cue.start_ms <= 7000);
+        }
+        // The first cut falls after the comma rather than mid-clause
+        assert!(cues[0].text.ends_with("sentence,") || cues[0].text.ends_with("on."), "{:?}", cues[0].text);
+    }
+
+    #[test]
+    fn lines_are_balanced() {
+        assert_eq!(
+            wrap_lines("one two three four five six seven eight nine ten eleven", &NETFLIX),
+            vec!["one two three four five six", "seven eight nine ten eleven"]
+        );
+        assert_eq!(wrap_lines("Short line", &NETFLIX), vec!["Short line"]);
+    }
+
+    #[test]
+    fn short_cues_are_stretched_as_far_as_the_next_cue_allows() {
+        let transcript =
+            Transcript::new(vec![segment(0, 300, "Yes."), segment(500, 900, "No."), segment(5000, 5300, "Maybe.")]);
+        let cues = shape_segments(&transcript, &NETFLIX);
+        assert_eq!((cues[0].start_ms, cues[0].end_ms), (0, 500));
+        assert_eq!((cues[1].start_ms, cues[1].end_ms), (500, 1333));
+        assert_eq!((cues[2].start_ms, cues[2].end_ms), (5000, 5833));
+    }
+
+    #[test]
+    fn cues_too_fast_to_read_are_counted() {
+        // 42 characters need 2.1 s at 20 cps, but the next cue starts after 2 s
+        let line = "Forty two characters of dialogue, exactly.";
+        assert_eq!(line.chars().count(), 42);
+        let transcript = Transcript::new(vec![segment(0, 2000, line), segment(2000, 4000, "Then a pause.")]);
+        let cues = shape_segments(&transcript, &NETFLIX);
+        assert_eq!(cues[0].end_ms, 2000);
+        assert_eq!(count_fast_cues(&cues, &NETFLIX), 1);
+
+        let transcript = Transcript::new(vec![segment(0, 2000, line), segment(3000, 4000, "Then a pause.")]);
+        let cues = shape_segments(&transcript, &NETFLIX);
+        assert_eq!(cues[0].end_ms, 2100);
+        assert_eq!(count_fast_cues(&cues, &NETFLIX), 0);
+    }
+
+    #[test]
+    fn words_are_interpolated_by_length() {
+        let words = interpolate_words(&segment(1000, 2000, "a bbb"));
+        assert_eq!(
+            words.iter().map(|w| (w.text.as_str(), w.start_ms, w.end_ms)).collect::<Vec<_>>(),
+            vec![("a", 1000, 1250), ("bbb", 1250, 2000)]
+        );
+    }
+}
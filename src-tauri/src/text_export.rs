@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::output::{prepare_output, OutputNaming};
+use crate::transcript::{Segment, Transcript};
+
+/// Settings for the readable (untimed) transcript exports, persisted as the `transcript` selection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranscriptOptions {
+    /// Silence, in seconds, that starts a new paragraph.
+    pub paragraph_gap: f64,
+    /// Prefix Markdown paragraphs with `[HH:MM:SS]` markers.
+    pub markdown_timestamps: bool,
+    /// Minimum number of seconds between two Markdown timestamps.
+    pub timestamp_interval: u32,
+}
+
+impl Default for TranscriptOptions {
+    fn default() -> Self {
+        TranscriptOptions {
+            paragraph_gap: 2.0,
+            markdown_timestamps: true,
+            timestamp_interval: 60,
+        }
+    }
+}
+
+impl TranscriptOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.paragraph_gap.is_finite() || self.paragraph_gap <= 0.0 {
+            return Err(format!("Paragraph gap must be a positive number of seconds, got {}", self.paragraph_gap));
+        }
+        if self.timestamp_interval == 0 {
+            return Err("Timestamp interval must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// One of the transcript formats written by [`create_transcript`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Text,
+    Markdown,
+    Csv,
+    Tsv,
+}
+
+impl TranscriptFormat {
+    /// Maps an entry of the `formats` selection onto a transcript format.
+    pub fn parse(format: &str) -> Option<TranscriptFormat> {
+        match format {
+            "txt" => Some(TranscriptFormat::Text),
+            "md" => Some(TranscriptFormat::Markdown),
+            "csv" => Some(TranscriptFormat::Csv),
+            "tsv" => Some(TranscriptFormat::Tsv),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            TranscriptFormat::Text => "txt",
+            TranscriptFormat::Markdown => "md",
+            TranscriptFormat::Csv => "csv",
+            TranscriptFormat::Tsv => "tsv",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TranscriptFormat::Text => "Transcript",
+            TranscriptFormat::Markdown => "Markdown",
+            TranscriptFormat::Csv => "CSV",
+            TranscriptFormat::Tsv => "TSV",
+        }
+    }
+}
+
+pub fn create_transcript(
+    transcript: &Transcript,
+    format: TranscriptFormat,
+    options: &TranscriptOptions,
+    naming: &OutputNaming,
+    app: AppHandle,
+) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, format.extension(), &app) else {
+        return Ok(String::new());
+    };
+
+    let content = render_transcript(transcript, format, options);
+    let mut file = File::create(&file_path_buf)
+        .map_err(|e| format!("Failed to create {} file: {}", format.label(), e))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write to {} file: {}", format.label(), e))?;
+
+    app.emit("subtitle_created", format!("{} Created :: {}", format.label(), file_path_buf.display()))
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+
+    Ok(file_path_buf.to_string_lossy().to_string())
+}
+
+/// Renders `transcript` in one of the untimed transcript formats.
+pub fn render_transcript(transcript: &Transcript, format: TranscriptFormat, options: &TranscriptOptions) -> String {
+    match format {
+        TranscriptFormat::Text => render_text(transcript, options),
+        TranscriptFormat::Markdown => render_markdown(transcript, options),
+        TranscriptFormat::Csv => render_table(transcript, ','),
+        TranscriptFormat::Tsv => render_table(transcript, '\t'),
+    }
+}
+
+/// Plain text with one paragraph per stretch of speech.
+pub fn render_text(transcript: &Transcript, options: &TranscriptOptions) -> String {
+    paragraphs(transcript, options.paragraph_gap)
+        .iter()
+        .map(|paragraph| paragraph.iter().map(|segment| segment.text.trim()).collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        + "\n"
+}
+
+/// Markdown paragraphs, optionally opened by a timestamp once at least `timestamp_interval`
+/// seconds have passed since the previous one.
+pub fn render_markdown(transcript: &Transcript, options: &TranscriptOptions) -> String {
+    let interval_ms = options.timestamp_interval.max(1) as u64 * 1000;
+    let mut next_timestamp = 0;
+
+    paragraphs(transcript, options.paragraph_gap)
+        .iter()
+        .map(|paragraph| {
+            let mut line = String::new();
+            for segment in paragraph {
+                if options.markdown_timestamps && segment.start_ms >= next_timestamp {
+                    if !line.is_empty() {
+                        line.push(' ');
+                    }
+                    line.push_str(&format!("**[{}]**", ms_to_clock(segment.start_ms)));
+                    next_timestamp = (segment.start_ms / interval_ms + 1) * interval_ms;
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&escape_markdown(segment.text.trim()));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+        + "\n"
+}
+
+/// One row per segment with `start`, `end`, `text` and `confidence` columns.
+///
+/// Times are seconds with millisecond precision so spreadsheets can do arithmetic on them.
+pub fn render_table(transcript: &Transcript, delimiter: char) -> String {
+    let mut table = ["start", "end", "text", "confidence"].join(&delimiter.to_string());
+    table.push('\n');
+
+    for segment in transcript.cues() {
+        let row = [
+            format!("{:.3}", segment.start()),
+            format!("{:.3}", segment.end()),
+            table_cell(segment.text.trim(), delimiter),
+            segment.confidence.map(|c| format!("{:.3}", c)).unwrap_or_default(),
+        ];
+        table.push_str(&row.join(&delimiter.to_string()));
+        table.push('\n');
+    }
+
+    table
+}
+
+/// Groups non-empty segments into paragraphs, breaking on pauses of at least `gap` seconds.
+fn paragraphs(transcript: &Transcript, gap: f64) -> Vec<Vec<&Segment>> {
+    let gap_ms = (gap * 1000.0).round() as u64;
+    let mut paragraphs: Vec<Vec<&Segment>> = Vec::new();
+    let mut previous_end: Option<u64> = None;
+
+    for segment in transcript.cues() {
+        match (paragraphs.last_mut(), previous_end) {
+            (Some(paragraph), Some(previous_end)) if segment.start_ms.saturating_sub(previous_end) < gap_ms => {
+                paragraph.push(segment)
+            }
+            _ => paragraphs.push(vec![segment]),
+        }
+        previous_end = Some(segment.end_ms);
+    }
+
+    paragraphs
+}
+
+/// Quotes CSV cells per RFC 4180; TSV has no quoting, so tabs and line breaks become spaces.
+fn table_cell(text: &str, delimiter: char) -> String {
+    if delimiter == '\t' {
+        return text.replace(['\t', '\r', '\n'], " ");
+    }
+    if text.contains([delimiter, '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn ms_to_clock(ms: u64) -> String {
+    let total = ms / 1000;
+    format!("{:02}:{:02}:{:02}", total / 3600, (total % 3600) / 60, total % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment {
+            start_ms,
+            end_ms,
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pauses_start_new_paragraphs() {
+        let transcript = Transcript::new(vec![
+            segment(0, 1000, " Hello."),
+            segment(2900, 3500, "How are you?"),
+            segment(3500, 4000, "  "),
+            segment(5500, 6000, "Fine."),
+        ]);
+        let options = TranscriptOptions::default();
+        assert_eq!(render_text(&transcript, &options), "Hello. How are you?\n\nFine.\n");
+    }
+
+    #[test]
+    fn markdown_timestamps_follow_the_interval() {
+        let transcript = Transcript::new(vec![
+            segment(0, 1000, "Intro *one*"),
+            segment(30_000, 31_000, "Still"),
+            segment(61_000, 62_000, "Later"),
+            segment(62_500, 63_000, "more"),
+            segment(3_725_000, 3_726_000, "Much later"),
+        ]);
+        let options = TranscriptOptions::default();
+        assert_eq!(
+            render_markdown(&transcript, &options),
+            "**[00:00:00]** Intro \\*one\\*\n\nStill\n\n**[00:01:01]** Later more\n\n**[01:02:05]** Much later\n"
+        );
+
+        let untimed = TranscriptOptions {
+            markdown_timestamps: false,
+            ..Default::default()
+        };
+        assert!(!render_markdown(&transcript, &untimed).contains("**["));
+    }
+
+    #[test]
+    fn table_cells_are_quoted_or_flattened() {
+        let mut quoted = segment(1500, 2250, "Hello, \"you\"\nthere\tnow");
+        quoted.confidence = Some(0.9);
+        let transcript = Transcript::new(vec![quoted, segment(3000, 4000, "plain")]);
+
+        assert_eq!(
+            render_table(&transcript, ','),
+            "start,end,text,confidence\n1.500,2.250,\"Hello, \"\"you\"\"\nthere\tnow\",0.900\n3.000,4.000,plain,\n"
+        );
+        assert_eq!(
+            render_table(&transcript, '\t'),
+            "start\tend\ttext\tconfidence\n1.500\t2.250\tHello, \"you\" there now\t0.900\n3.000\t4.000\tplain\t\n"
+        );
+    }
+}
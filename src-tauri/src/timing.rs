@@ -0,0 +1,145 @@
+use crate::transcript::Segment;
+
+/// Shortest cue the validation pass leaves behind, long enough for a player to render a frame.
+pub const MIN_CUE_MS: u64 = 40;
+
+/// Converts whisper's centisecond timestamps to milliseconds, clamping the `-1` "no timing" marker to 0.
+pub fn centis_to_ms(centis: i64) -> u64 {
+    centis.max(0) as u64 * 10
+}
+
+/// Rounds seconds to whole milliseconds once, so every formatter works on the same integer.
+pub fn sec_to_ms(sec: f64) -> u64 {
+    if sec.is_finite() && sec > 0.0 {
+        (sec * 1000.0).round() as u64
+    } else {
+        0
+    }
+}
+
+/// `HH:MM:SS<separator>mmm`, e.g. `,` for SRT and `.` for WebVTT.
+pub fn format_timestamp(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        (ms % 3_600_000) / 60_000,
+        (ms % 60_000) / 1000,
+        separator,
+        ms % 1000
+    )
+}
+
+/// Repairs cue timing in place before any writer sees it and returns how many cues were
+/// merged or retimed.
+///
+/// Cues are ordered by start time, and cues that start at the same time are merged into
+/// one cue showing all of their lines until the last of them ends. Negative and
+/// zero-length cues are then given [`MIN_CUE_MS`] (or whatever room there is before the
+/// next cue), and a cue that runs into the next one is cut off where the next one starts.
+pub fn sanitize_cues(segments: &mut Vec<Segment>) -> usize {
+    segments.sort_by_key(|segment| segment.start_ms);
+
+    let mut changed = 0;
+    let mut merged: Vec<Segment> = Vec::with_capacity(segments.len());
+    for segment in segments.drain(..) {
+        match merged.last_mut() {
+            Some(previous) if previous.start_ms == segment.start_ms => {
+                previous.end_ms = previous.end_ms.max(segment.end_ms);
+                previous.append_line(&segment);
+                previous.words.extend(segment.words);
+                changed += 1;
+            }
+            _ => merged.push(segment),
+        }
+    }
+    *segments = merged;
+
+    for i in 0..segments.len() {
+        let start = segments[i].start_ms;
+        let mut end = if segments[i].end_ms <= start { start + MIN_CUE_MS } else { segments[i].end_ms };
+        if let Some(next) = segments.get(i + 1) {
+            end = end.min(next.start_ms);
+        }
+
+        if end != segments[i].end_ms {
+            segments[i].end_ms = end;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{StyleSpan, TextStyle};
+
+    fn cue(start_ms: u64, end_ms: u64, text: &str) -> Segment {
+        Segment { start_ms, end_ms, text: text.to_string(), ..Default::default() }
+    }
+
+    fn timings(segments: &[Segment]) -> Vec<(u64, u64, &str)> {
+        segments.iter().map(|s| (s.start_ms, s.end_ms, s.text.as_str())).collect()
+    }
+
+    #[test]
+    fn milliseconds_never_reach_1000() {
+        // Rounding the fraction on its own used to print 00:00:01,1000
+        assert_eq!(format_timestamp(sec_to_ms(1.9996), ','), "00:00:02,000");
+        assert_eq!(format_timestamp(sec_to_ms(59.9999), '.'), "00:01:00.000");
+        assert_eq!(format_timestamp(3_723_004, ','), "01:02:03,004");
+        assert_eq!(sec_to_ms(-0.5), 0);
+        assert_eq!(sec_to_ms(f64::NAN), 0);
+        assert_eq!(centis_to_ms(-1), 0);
+        assert_eq!(centis_to_ms(199), 1990);
+    }
+
+    #[test]
+    fn overlaps_are_cut_where_the_next_cue_starts() {
+        let mut segments = vec![cue(3000, 4000, "c"), cue(0, 2500, "a"), cue(2000, 3500, "b")];
+        assert_eq!(sanitize_cues(&mut segments), 2);
+        assert_eq!(timings(&segments), vec![(0, 2000, "a"), (2000, 3000, "b"), (3000, 4000, "c")]);
+    }
+
+    #[test]
+    fn zero_length_and_negative_cues_get_the_minimum_length() {
+        let mut segments = vec![cue(1000, 1000, "a"), cue(2000, 1500, "b"), cue(2020, 3000, "c")];
+        assert_eq!(sanitize_cues(&mut segments), 2);
+        assert_eq!(
+            timings(&segments),
+            vec![(1000, 1000 + MIN_CUE_MS, "a"), (2000, 2020, "b"), (2020, 3000, "c")]
+        );
+    }
+
+    #[test]
+    fn cues_with_the_same_start_are_merged() {
+        let mut segments = vec![cue(1000, 5000, "a"), cue(1000, 2000, "b"), cue(1500, 1600, "c")];
+        sanitize_cues(&mut segments);
+        assert_eq!(timings(&segments), vec![(1000, 1500, "a\nb"), (1500, 1600, "c")]);
+    }
+
+    #[test]
+    fn merged_cues_keep_their_styling() {
+        let mut first = cue(1000, 2000, " top ");
+        first.styles = vec![StyleSpan { start: 1, end: 4, style: TextStyle::Italic }];
+        let mut second = cue(1000, 3000, "bottom");
+        second.styles = vec![StyleSpan { start: 0, end: 6, style: TextStyle::Bold }];
+        let mut segments = vec![first, second];
+        sanitize_cues(&mut segments);
+        assert_eq!(segments[0].text, "top\nbottom");
+        assert_eq!(
+            segments[0].styles,
+            vec![
+                StyleSpan { start: 0, end: 3, style: TextStyle::Italic },
+                StyleSpan { start: 4, end: 10, style: TextStyle::Bold },
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_cues_are_left_alone() {
+        let mut segments = vec![cue(0, 1000, "a"), cue(1000, 2000, "b")];
+        assert_eq!(sanitize_cues(&mut segments), 0);
+        assert_eq!(timings(&segments), vec![(0, 1000, "a"), (1000, 2000, "b")]);
+    }
+}
@@ -1,56 +1,205 @@
 use anyhow::Result;
-use std::fs::File;
-use std::io::Read;
-use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
-use std::env;
-use std::fs;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperTokenData};
 
+use crate::audio::{decode_media, get_audio_duration, read_media_tags, read_wav_samples, WHISPER_SAMPLE_RATE};
+use crate::job::{cancel_running_jobs, JobHandle, CANCELLED_MESSAGE};
+use crate::output::{
+    output_extension, parse_output_formats, CollisionPolicy, OutputNaming, DEFAULT_NAMING_TEMPLATE,
+    DEFAULT_OUTPUT_FORMATS,
+};
+use crate::ass::{create_ass, AssStyle, DEFAULT_RESOLUTION};
+use crate::lrc::{create_lrc, LrcOptions};
+use crate::model_cache::models_dir;
+use crate::models::ModelRegistry;
+use crate::retime::{retime_segments, RetimeOptions};
+use crate::shaping::{count_fast_cues, shape_segments, ShapingOptions};
+use crate::timing::{centis_to_ms, sanitize_cues, sec_to_ms};
+use crate::transcript::{Segment, Transcript, Word};
+use crate::text_export::{create_transcript, TranscriptFormat, TranscriptOptions};
+use crate::ttml::{create_ttml, FrameRate, TtmlProfile, DEFAULT_FRAME_RATE};
+use crate::vtt::{create_vtt, VttOptions};
 use crate::utils::{
-    create_srt, create_json, download_model, extract_audio, get_audio_duration, is_video_or_audio,
-    load_selection,
+    create_srt, create_json, download_model, emit_error, extract_audio, is_video_or_audio,
+    json_selection, probe_video, save_selection, FfmpegLocator, MetaData, SelectedData,
 };
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
+/// Runs a transcription with `selections` (the same key/value pairs `save_selection`
+/// takes) applied over the saved ones.
+///
+/// The job works from that snapshot rather than reading the settings file back, so
+/// another instance saving its own selections in the meantime cannot redirect it.
 #[tauri::command]
-pub async fn start_transcription(app: AppHandle) -> Result<(), String> {
-    app.emit("transcription_started", "TRANSCRIPTION_STARTED").ok();
+pub async fn start_transcription(
+    app: AppHandle,
+    task: Option<String>,
+    selections: Option<Vec<MetaData>>,
+) -> Result<(), String> {
+    let mut selections = selections.unwrap_or_default();
+    if let Some(task) = task {
+        selections.push(MetaData::new("task", &task));
+    }
+    let mut snapshot = SelectedData::load()?;
+    snapshot.apply(selections.clone())?;
+    // Remembered for the next launch; the job itself only uses the snapshot
+    save_selection(selections, app.clone())?;
+
+    let workspace_root = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| format!("Failed to resolve app cache directory: {}", e))?
+        .join("jobs");
+    let job = JobHandle::start(&workspace_root)
+        .map_err(|e| format!("Failed to create job workspace: {}", e))?;
+    let result = run_transcription(&app, &job, &snapshot).await;
+
+    if job.is_cancelled() {
+        job.cleanup_partial_files();
+        job.finish();
+        app.emit("transcription_cancelled", CANCELLED_MESSAGE)
+            .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+        return Ok(());
+    }
 
-    let model = validate_and_load_selection(&app, "model", "Model file not found")?;
-    let media_file = validate_and_load_selection(&app, "file", "Media File not found")?;
-    let media_folder = validate_and_load_selection(&app, "folder", "Output Folder not found")?;
+    job.finish();
+    result
+}
 
-    validate_path_exists(&app, &media_file, "Media File not found at path")?;
-    validate_path_exists(&app, &media_folder, "Output Folder not found at path")?;
+/// Selection snapshot taken when a job starts, so edits made while it runs
+/// (or by another app instance) cannot redirect its output half way through.
+struct TranscriptionSettings {
+    media_file: String,
+    model: String,
+    model_path: String,
+    output_folder: String,
+    ffmpeg_path: Option<String>,
+    language: String,
+    task: String,
+    naming_template: String,
+    collision_policy: CollisionPolicy,
+    output_formats: Vec<String>,
+    vtt_options: VttOptions,
+    ass_style: AssStyle,
+    transcript_options: TranscriptOptions,
+    lrc_options: LrcOptions,
+    shaping_options: ShapingOptions,
+    retime_options: RetimeOptions,
+}
 
-    let (download_url, mut new_path_parent) = match_model(&model)?;
+async fn run_transcription(app: &AppHandle, job: &JobHandle, selections: &SelectedData) -> Result<(), String> {
+    app.emit("transcription_started", "TRANSCRIPTION_STARTED").ok();
 
-    if download_url.starts_with("https://") {
-        let app_clone = app.clone();
-        match download_model(&download_url, &new_path_parent, app_clone).await {
-            Ok(new_path) => {
-                new_path_parent = new_path.to_string_lossy().to_string();
-                app.emit("success", format!("Downloaded model: {}", model))
-                    .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-            }
-            Err(e) => {
-                app.emit("error", format!("Error downloading model: {}", e))
-                    .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
-                return Err(format!("Error downloading model: {}", e));
+    let model = validate_and_load_selection(app, selections, "model", "Model file not found")?;
+    let media_file = validate_and_load_selection(app, selections, "file", "Media File not found")?;
+    let media_folder = validate_and_load_selection(app, selections, "folder", "Output Folder not found")?;
+
+    let language = selections
+        .get("language")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "en".to_string());
+    let task = selections
+        .get("task")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "transcribe".to_string());
+
+    let naming_template = selections
+        .get("naming")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| DEFAULT_NAMING_TEMPLATE.to_string());
+    let collision_policy = selections
+        .get("collision")
+        .ok()
+        .flatten()
+        .and_then(|policy| CollisionPolicy::parse(&policy))
+        .unwrap_or(CollisionPolicy::Overwrite);
+    let output_formats = selections
+        .get("formats")
+        .ok()
+        .flatten()
+        .and_then(|formats| parse_output_formats(&formats).ok())
+        .unwrap_or_else(|| parse_output_formats(DEFAULT_OUTPUT_FORMATS).unwrap_or_default());
+    let vtt_options = json_selection(selections, "vtt");
+    let ass_style = json_selection(selections, "ass");
+    let transcript_options = json_selection(selections, "transcript");
+    let lrc_options = json_selection(selections, "lrc");
+    let shaping_options = json_selection(selections, "shaping");
+    let retime_options = json_selection(selections, "retime");
+
+    validate_path_exists(app, &media_file, "Media File not found at path")?;
+    validate_path_exists(app, &media_folder, "Output Folder not found at path")?;
+
+    let registry = ModelRegistry::load(app).inspect_err(|e| emit_error(app, e))?;
+    let entry = registry
+        .resolve(&model, &language, &task)
+        .inspect_err(|e| emit_error(app, e))?
+        .cloned();
+
+    let model_path = match &entry {
+        Some(entry) => {
+            let target = models_dir(app).inspect_err(|e| emit_error(app, e))?.join(&entry.file_name);
+            match download_model(entry, &target, app.clone(), job).await {
+                Ok(new_path) => {
+                    app.emit("success", format!("Downloaded model: {}", entry.name))
+                        .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+                    new_path.to_string_lossy().to_string()
+                }
+                Err(e) => {
+                    if job.is_cancelled() {
+                        return Err(CANCELLED_MESSAGE.to_string());
+                    }
+                    app.emit("error", format!("Error downloading model: {}", e))
+                        .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
+                    return Err(format!("Error downloading model: {}", e));
+                }
             }
         }
+        // Anything that is not a registry name is the path of a local model file
+        None => model.clone(),
+    };
+
+    validate_path_exists(app, &model_path, "Model file not found at path")?;
+
+    if task == "translate" && entry.as_ref().is_some_and(|entry| !entry.translates) {
+        app.emit("info", format!("{} was not trained for translation; output may stay in the source language", model))
+            .unwrap_or_else(|e| eprintln!("Emit error: {}", e));
     }
 
-    validate_path_exists(&app, &new_path_parent, "Model file not found at path")?;
+    let settings = TranscriptionSettings {
+        media_file,
+        model,
+        model_path,
+        output_folder: media_folder,
+        ffmpeg_path: selections.get("ffmpeg").ok().flatten(),
+        language,
+        task,
+        naming_template,
+        collision_policy,
+        output_formats,
+        vtt_options,
+        ass_style,
+        transcript_options,
+        lrc_options,
+        shaping_options,
+        retime_options,
+    };
 
-    transcribe_with_whisper(media_file, &new_path_parent, app)
+    transcribe_with_whisper(&settings, app.clone(), job)
         .await
         .map_err(|e| e.to_string())
 }
 
-fn validate_and_load_selection(app: &AppHandle, key: &str, error_message: &str) -> Result<String, String> {
-    load_selection(key.to_string())
+fn validate_and_load_selection(
+    app: &AppHandle,
+    selections: &SelectedData,
+    key: &str,
+    error_message: &str,
+) -> Result<String, String> {
+    selections
+        .get(key)
         .map_err(|e| {
             app.emit("error", error_message).ok();
             format!("Error loading {}: {}", key, e)
@@ -70,104 +219,48 @@ fn validate_path_exists(app: &AppHandle, path: &str, error_message: &str) -> Res
     }
 }
 
-fn match_model(model: &str) -> Result<(String, String), String> {
-    let temp_dir = env::temp_dir();
-    let srtify_dir = temp_dir.join("srtify");
-    fs::create_dir_all(&srtify_dir).expect("Failed to create srtify directory");
-
-    let (download_url, model_filename) = match model {
-        "whisper-base" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-base.en.bin".to_string(),
-            srtify_dir.join("ggml-base.en.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-tiny" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-tiny.en.bin".to_string(),
-            srtify_dir.join("ggml-tiny.en.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-small" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-small.en.bin".to_string(),
-            srtify_dir.join("ggml-small.en.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-medium" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-medium.en.bin".to_string(),
-            srtify_dir.join("ggml-medium.en.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-large-v1" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v1.bin".to_string(),
-            srtify_dir.join("ggml-large-v1.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-large-v2" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v2.bin".to_string(),
-            srtify_dir.join("ggml-large-v2.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-large-v3" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3.bin".to_string(),
-            srtify_dir.join("ggml-large-v3.bin").to_string_lossy().to_string(),
-        ),
-        "whisper-large-v3-turbo" => (
-            "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/ggml-large-v3-turbo.bin".to_string(),
-            srtify_dir.join("ggml-large-v3-turbo.bin").to_string_lossy().to_string(),
-        ),
-        _ => (model.to_string(), model.to_string()),
-    };
-    Ok((download_url, model_filename))
-}
-
 async fn transcribe_with_whisper(
-    mut file_path: String,
-    model_name: &str,
+    settings: &TranscriptionSettings,
     app: AppHandle,
+    job: &JobHandle,
 ) -> Result<()> {
-    if let Some(media_type) = is_video_or_audio(&file_path).as_deref() {
-        if media_type == "video" || media_type == "audio" {
-            app.emit("info", "Extracting/Converting audio to WAV using ffmpeg").unwrap_or_else(|e| {
-                eprintln!("Emit error: {}", e);
-            });
-            let app_clone = app.clone();
-            file_path = match extract_audio(&file_path, app_clone) {
-                Ok(path) => path,
-                Err(_) => return Err(anyhow::anyhow!("Error in extract_audio")),
-            };
-            app.emit("info", "Audio ready in WAV format").unwrap_or_else(|e| {
-                eprintln!("Emit error: {}", e);
-            });
-        }
-    }
+    let language = &settings.language;
+    let task = &settings.task;
 
-    let mut file = File::open(file_path.clone())?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
-
-    let samples: Vec<f32> = buffer
-        .chunks_exact(2)
-        .map(|chunk| {
-            let sample = i16::from_le_bytes([chunk[0], chunk[1]]);
-            sample as f32 / 32768.0 // Normalize to [-1.0, 1.0]
-        })
-        .collect();
-
-    let duration = match get_audio_duration(&file_path) {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("Error getting audio duration: {}", e);
-            return Err(e.into()); // Convert to your error type
-        }
-    };
+    // Resolved on first use, then shared by audio extraction and the video probe
+    let ffmpeg = FfmpegLocator::new(&app, settings.ffmpeg_path.clone());
+    let samples = load_audio_samples(&settings.media_file, &ffmpeg, &app, job).map_err(anyhow::Error::msg)?;
+    let duration = samples.len() as f64 / WHISPER_SAMPLE_RATE as f64;
 
     let params = WhisperContextParameters::default();
-    let ctx = WhisperContext::new_with_params(&model_name, params)?;
+    let ctx = WhisperContext::new_with_params(&settings.model_path, params)?;
     let mut state = ctx.create_state()?;
 
+    let threads = std::thread::available_parallelism()
+        .map(|n| n.get().min(4))
+        .unwrap_or(1);
+
+    // Detect up front when asked to, so the confidence can be reported alongside the result
+    let mut language_probability = None;
+    if language == "auto" {
+        app.emit("info", "Detecting spoken language").unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+        state.pcm_to_mel(&samples, threads)?;
+        let (lang_id, probabilities) = state.lang_detect(0, threads)?;
+        language_probability = probabilities.get(lang_id as usize).copied();
+    }
+
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(threads as i32);
+    params.set_language(Some(language.as_str()));
+    params.set_translate(task == "translate");
     params.set_token_timestamps(true);
     params.set_print_realtime(false);
     params.set_print_progress(false);
     params.set_print_timestamps(false);
     params.set_print_special(false);
 
-    let subtitles = Arc::new(Mutex::new(Vec::new()));
-    let subtitles_clone = subtitles.clone();
-
     let app_clone = app.clone();
     app.emit("transcription_started", "TRANSCRIPTION_STARTED")
         .unwrap_or_else(|e| {
@@ -187,71 +280,290 @@ async fn transcribe_with_whisper(
             eprintln!("Emit error: {}", e);
         }
         println!("{}", message);
-
-        if let Ok(mut subtitles) = subtitles_clone.lock() {
-            subtitles.push((
-                data.text.clone(),
-                data.start_timestamp as f64 * 0.01,
-                data.end_timestamp as f64 * 0.01,
-            ));
-        }
     });
 
+    // whisper.cpp polls this between decoder steps and bails out once it returns true
+    let abort_job = job.clone();
+    params.set_abort_callback_safe(move || abort_job.is_cancelled());
+
+    if job.is_cancelled() {
+        return Err(anyhow::anyhow!(CANCELLED_MESSAGE));
+    }
     state.full(params, &samples)?;
+    if job.is_cancelled() {
+        return Err(anyhow::anyhow!(CANCELLED_MESSAGE));
+    }
+    let detected_language = if language == "auto" {
+        state
+            .full_lang_id_from_state()
+            .ok()
+            .and_then(whisper_rs::get_lang_str)
+            .unwrap_or("auto")
+            .to_string()
+    } else {
+        language.clone()
+    };
+    if let Some(probability) = language_probability {
+        app.emit("info", format!("Detected language: {} ({:.1}%)", detected_language, probability * 100.0))
+            .unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+    }
+
+    let extensions: Vec<&str> = settings.output_formats.iter().filter_map(|f| output_extension(f)).collect();
+    let naming = OutputNaming::new(
+        &settings.output_folder,
+        &settings.naming_template,
+        &settings.media_file,
+        &detected_language,
+        &settings.model,
+        settings.collision_policy,
+        &extensions,
+    );
+
     app.emit("transcription_complete", "TRANSCRIPTION_COMPLETE")
         .unwrap_or_else(|e| {
             eprintln!("Emit error: {}", e);
         });
 
-    if let Err(e) = create_srt(subtitles.lock().unwrap().clone(), app.clone()) {
-        eprintln!("Error creating SRT: {}", e);
-    }
-
     // --- Word-level extraction ---
-    let mut all_segments = Vec::new();
+    let mut segments = Vec::new();
     let n_segments = state.full_n_segments()?;
 
     for i in 0..n_segments {
-        let segment_start = state.full_get_segment_t0(i)? as f64 * 0.01;
-        let segment_end = state.full_get_segment_t1(i)? as f64 * 0.01;
         let text = state.full_get_segment_text(i)?;
 
-        let mut words = Vec::new();
+        let mut words: Vec<Word> = Vec::new();
+        let mut token_probabilities = Vec::new();
         let n_tokens = state.full_n_tokens(i)?;
         for j in 0..n_tokens {
             let token_text = state.full_get_token_text(i, j)?;
             let token_data = state.full_get_token_data(i, j)?;
-            let token_t0 = token_data.t0;
-            let token_t1 = token_data.t1;
-
-            // Only include tokens that have timing info
-            if token_t0 >= 0 && token_t1 >= 0 {
-                words.push(serde_json::json!({
-                    "word": token_text.trim(),
-                    "start": token_t0 as f64 * 0.01,
-                    "end": token_t1 as f64 * 0.01
-                }));
+
+            // Only include text tokens that have timing info
+            if token_data.t0 >= 0 && token_data.t1 >= 0 && !is_special_token(&token_text) {
+                push_token(&mut words, &token_text, &token_data);
+                token_probabilities.push(token_data.p);
             }
         }
 
-        all_segments.push(serde_json::json!({
-            "text": text.trim(),
-            "start": segment_start,
-            "end": segment_end,
-            "words": words
-        }));
+        // Mean token probability, a rough per-segment confidence score
+        let confidence = if token_probabilities.is_empty() {
+            None
+        } else {
+            Some(token_probabilities.iter().sum::<f32>() / token_probabilities.len() as f32)
+        };
+
+        segments.push(Segment {
+            start_ms: centis_to_ms(state.full_get_segment_t0(i)?),
+            end_ms: centis_to_ms(state.full_get_segment_t1(i)?),
+            text: text.trim().to_string(),
+            confidence,
+            words,
+            ..Default::default()
+        });
+    }
+
+    // Move everything onto the timeline of a re-edited video before any writer sees it
+    if !settings.retime_options.is_identity() {
+        let dropped = retime_segments(&mut segments, &settings.retime_options);
+        let mut message = "Retimed subtitles".to_string();
+        if dropped > 0 {
+            message.push_str(&format!(", dropped {} segment(s) that now end before the start", dropped));
+        }
+        app.emit("info", message).unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
     }
 
-    // Save JSON file
-    if let Err(e) = create_json(all_segments, app.clone()) {
-        eprintln!("Error creating JSON: {}", e);
+    // whisper occasionally emits segments that end before they start or overlap their successor
+    let repaired = sanitize_cues(&mut segments);
+    if repaired > 0 {
+        app.emit("info", format!("Merged or adjusted timing of {} overlapping or empty segment(s)", repaired))
+            .unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+    }
+
+    // The container's own length covers the whole file, the decoded samples are the fallback
+    let duration = get_audio_duration(&settings.media_file).unwrap_or(duration);
+    let transcript = Transcript {
+        // Translations are always English, whatever was spoken
+        language: Some(if task == "translate" { "en".to_string() } else { detected_language.clone() }),
+        language_probability,
+        task: Some(task.clone()),
+        model: Some(settings.model.clone()),
+        source: Path::new(&settings.media_file)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string()),
+        duration_ms: Some(sec_to_ms(duration)),
+        ..Transcript::new(segments)
+    };
+
+    // Timed subtitle formats get readable cues, transcripts and JSON keep whisper's segments
+    let rules = settings.shaping_options.rules();
+    let mut subtitle_segments = match &rules {
+        Some(rules) => shape_segments(&transcript, rules),
+        None => transcript.segments.clone(),
+    };
+    sanitize_cues(&mut subtitle_segments);
+    if let Some(rules) = &rules {
+        let fast = count_fast_cues(&subtitle_segments, rules);
+        if fast > 0 {
+            let message = format!(
+                "{} cue(s) read faster than {} characters per second, the next cue left no room to lengthen them",
+                fast, rules.max_cps
+            );
+            app.emit("info", message).unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+        }
+    }
+    let subtitles = transcript.with_segments(subtitle_segments);
+
+    if settings.output_formats.iter().any(|f| f == "srt") {
+        if let Err(e) = create_srt(&subtitles, &naming, app.clone()) {
+            eprintln!("Error creating SRT: {}", e);
+        }
+    }
+
+    if settings.output_formats.iter().any(|f| f == "vtt") {
+        if let Err(e) = create_vtt(&subtitles, &settings.vtt_options, &naming, app.clone()) {
+            eprintln!("Error creating WebVTT: {}", e);
+        }
+    }
+
+    let needs_video_info = settings.output_formats.iter().any(|f| matches!(f.as_str(), "ass" | "ttml" | "ebu-tt-d"));
+    let video_info = if needs_video_info { probe_video(&settings.media_file, &ffmpeg) } else { None };
+
+    if settings.output_formats.iter().any(|f| f == "ass") {
+        // Match the script to the video frame so positions and font sizes map 1:1 to pixels
+        let resolution = video_info.map(|v| (v.width, v.height)).unwrap_or(DEFAULT_RESOLUTION);
+        if let Err(e) = create_ass(&subtitles, &settings.ass_style, resolution, &naming, app.clone()) {
+            eprintln!("Error creating ASS: {}", e);
+        }
+    }
+
+    let frame_rate = FrameRate::from_fps(video_info.and_then(|v| v.frame_rate).unwrap_or(DEFAULT_FRAME_RATE));
+    for (format, profile) in [("ttml", TtmlProfile::Imsc1), ("ebu-tt-d", TtmlProfile::EbuTtD)] {
+        if settings.output_formats.iter().any(|f| f == format) {
+            if let Err(e) = create_ttml(&subtitles, profile, frame_rate, &naming, app.clone()) {
+                eprintln!("Error creating TTML: {}", e);
+            }
+        }
+    }
+
+    for format in settings.output_formats.iter().filter_map(|f| TranscriptFormat::parse(f)) {
+        if let Err(e) = create_transcript(&transcript, format, &settings.transcript_options, &naming, app.clone()) {
+            eprintln!("Error creating transcript: {}", e);
+        }
+    }
+
+    if settings.output_formats.iter().any(|f| f == "lrc") {
+        let mut tags = read_media_tags(&settings.media_file);
+        if tags.title.is_none() {
+            tags.title = Path::new(&settings.media_file)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string());
+        }
+        if let Err(e) = create_lrc(&transcript, &settings.lrc_options, &tags, &naming, app.clone()) {
+            eprintln!("Error creating LRC: {}", e);
+        }
+    }
+
+    if settings.output_formats.iter().any(|f| f == "json") {
+        if let Err(e) = create_json(&transcript, &naming, app.clone()) {
+            eprintln!("Error creating JSON: {}", e);
+        }
     }
 
     Ok(())
 }
 
+/// Special tokens such as `[_BEG_]`, `[_TT_150]` or `<|endoftext|>` carry no text.
+fn is_special_token(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("[_") && text.ends_with(']')) || (text.starts_with("<|") && text.ends_with("|>"))
+}
+
+/// Whisper emits sub-word BPE tokens; a token starting with a space begins a new
+/// word, anything else is glued onto the previous word and extends its end time.
+/// A word's confidence is the mean probability of its tokens.
+fn push_token(words: &mut Vec<Word>, token: &str, data: &WhisperTokenData) {
+    let starts_word = token.starts_with(' ') || words.is_empty();
+    let text = token.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    match words.last_mut() {
+        Some(word) if !starts_word => {
+            let tokens = word.token_ids.len() as f32;
+            word.text.push_str(text);
+            word.end_ms = centis_to_ms(data.t1);
+            word.confidence = word.confidence.map(|c| (c * tokens + data.p) / (tokens + 1.0));
+            word.token_ids.push(data.id);
+        }
+        _ => words.push(Word {
+            text: text.to_string(),
+            start_ms: centis_to_ms(data.t0),
+            end_ms: centis_to_ms(data.t1),
+            confidence: Some(data.p),
+            token_ids: vec![data.id],
+        }),
+    }
+}
+
+/// Produces 16 kHz mono samples for whisper, decoding natively where possible and
+/// only shelling out to ffmpeg for containers the native decoder cannot handle.
+///
+/// Failures are reported through the `error` event before being returned.
+fn load_audio_samples(file_path: &str, ffmpeg: &FfmpegLocator, app: &AppHandle, job: &JobHandle) -> Result<Vec<f32>, String> {
+    if is_video_or_audio(file_path).is_none() {
+        return read_wav_samples(file_path).inspect_err(|e| emit_error(app, e));
+    }
+
+    match decode_media(file_path, job) {
+        Ok(samples) => {
+            app.emit("info", "Audio decoded").unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+            return Ok(samples);
+        }
+        Err(e) if job.is_cancelled() => return Err(e),
+        Err(e) => {
+            app.emit("info", format!("Native decoding unavailable ({}), extracting audio using ffmpeg", e))
+                .unwrap_or_else(|e| {
+                    eprintln!("Emit error: {}", e);
+                });
+        }
+    }
+
+    let wav_path = match extract_audio(file_path, ffmpeg, job) {
+        Ok(path) => path,
+        Err(e) if job.is_cancelled() => return Err(e.to_string()),
+        Err(e) => {
+            app.emit("error", e.clone()).unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+            return Err(e.to_string());
+        }
+    };
+    app.emit("info", "Audio ready in WAV format").unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
+
+    read_wav_samples(&wav_path).inspect_err(|e| emit_error(app, e))
+}
+
 #[tauri::command]
-pub async fn stop_transcription() -> Result<(), String> {
+pub async fn stop_transcription(app: AppHandle) -> Result<(), String> {
     println!("Stop transcription invoked");
+    let cancelled = cancel_running_jobs();
+    if cancelled == 0 {
+        app.emit("info", "No transcription is running").ok();
+    } else {
+        app.emit("info", "Stopping transcription...").ok();
+    }
     Ok(())
 }
@@ -0,0 +1,372 @@
+use serde::{Deserialize, Serialize};
+
+/// Version of the `.words.json` layout below; bump it whenever a field changes meaning or is removed.
+pub const TRANSCRIPT_SCHEMA_VERSION: u32 = 1;
+
+/// A finished transcription, filled once by `transcribe_with_whisper` and handed to every writer.
+///
+/// Serialized as-is into `{name}.words.json`. Schema version 1:
+///
+/// ```json
+/// {
+///   "schema_version": 1,
+///   "language": "en",                 // spoken (or, when translating, output) language
+///   "language_probability": 0.97,     // only when the language was auto-detected
+///   "task": "transcribe",             // or "translate"
+///   "model": "whisper-base",
+///   "source": "interview.mp4",
+///   "duration_ms": 754200,
+///   "segments": [{
+///     "start_ms": 1200, "end_ms": 3450,
+///     "text": "Hello world.",
+///     "confidence": 0.91,             // mean token probability, 0..1
+///     "speaker": "S1",                // optional
+///     "language": "en",               // optional, when it differs per segment
+///     "styles": [{ "start": 0, "end": 5, "style": "italic" }], // optional, from imported subtitles
+///     "words": [{ "text": "Hello", "start_ms": 1200, "end_ms": 1610, "confidence": 0.95, "token_ids": [15947] }]
+///   }]
+/// }
+/// ```
+///
+/// Times are whole milliseconds; style ranges are UTF-8 byte offsets into `text`. Optional
+/// fields are omitted rather than written as `null`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transcript {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_probability: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+}
+
+/// One timed stretch of text: a whisper segment, or a cue after shaping.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Italic, bold and underlined stretches of `text`, kept from imported subtitles.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub styles: Vec<StyleSpan>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<Word>,
+}
+
+/// Inline styling that SRT, WebVTT, ASS and TTML can all express.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextStyle {
+    Italic,
+    Bold,
+    Underline,
+}
+
+impl TextStyle {
+    /// The letter SRT and WebVTT tags (`<i>`) and ASS overrides (`{\i1}`) use for the style.
+    pub fn code(self) -> &'static str {
+        match self {
+            TextStyle::Italic => "i",
+            TextStyle::Bold => "b",
+            TextStyle::Underline => "u",
+        }
+    }
+}
+
+/// `style` applied to the bytes `start..end` of a segment's text.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct StyleSpan {
+    pub start: usize,
+    pub end: usize,
+    pub style: TextStyle,
+}
+
+/// A stretch of a segment's trimmed text with the same styling throughout.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyledRun<'a> {
+    pub text: &'a str,
+    /// Sorted, without duplicates; empty for plain text.
+    pub styles: Vec<TextStyle>,
+}
+
+/// A whole word, assembled from one or more whisper BPE tokens.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub token_ids: Vec<i32>,
+}
+
+impl Transcript {
+    pub fn new(segments: Vec<Segment>) -> Transcript {
+        Transcript {
+            schema_version: TRANSCRIPT_SCHEMA_VERSION,
+            segments,
+            ..Default::default()
+        }
+    }
+
+    /// Reads a `.words.json` file, refusing schema versions this build does not understand.
+    pub fn from_json(json: &str) -> Result<Transcript, String> {
+        let transcript: Transcript =
+            serde_json::from_str(json).map_err(|e| format!("Invalid transcript JSON: {}", e))?;
+        if transcript.schema_version == 0 || transcript.schema_version > TRANSCRIPT_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported transcript schema version {} (expected 1..={})",
+                transcript.schema_version, TRANSCRIPT_SCHEMA_VERSION
+            ));
+        }
+        Ok(transcript)
+    }
+
+    /// The same transcript with different segments, e.g. shaped subtitle cues.
+    pub fn with_segments(&self, segments: Vec<Segment>) -> Transcript {
+        Transcript {
+            segments,
+            ..self.clone()
+        }
+    }
+
+    /// Segments that have something to show.
+    pub fn cues(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().filter(|segment| !segment.text.trim().is_empty())
+    }
+}
+
+impl Segment {
+    pub fn start(&self) -> f64 {
+        self.start_ms as f64 / 1000.0
+    }
+
+    pub fn end(&self) -> f64 {
+        self.end_ms as f64 / 1000.0
+    }
+
+    /// Splits the trimmed text into runs of equal styling. Style ranges that fall outside
+    /// the text or inside a character are ignored.
+    pub fn styled_runs(&self) -> Vec<StyledRun<'_>> {
+        let lead = self.text.len() - self.text.trim_start().len();
+        let text = self.text.trim();
+        let spans: Vec<(usize, usize, TextStyle)> = self
+            .styles
+            .iter()
+            .map(|span| (span.start.saturating_sub(lead).min(text.len()), span.end.saturating_sub(lead).min(text.len()), span.style))
+            .filter(|&(start, end, _)| start < end && text.is_char_boundary(start) && text.is_char_boundary(end))
+            .collect();
+
+        let mut bounds: Vec<usize> = spans.iter().flat_map(|&(start, end, _)| [start, end]).collect();
+        bounds.extend([0, text.len()]);
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut runs: Vec<(usize, usize, Vec<TextStyle>)> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let mut styles: Vec<TextStyle> = spans
+                .iter()
+                .filter(|&&(start, end, _)| start <= from && to <= end)
+                .map(|&(_, _, style)| style)
+                .collect();
+            styles.sort_unstable();
+            styles.dedup();
+            match runs.last_mut() {
+                Some(last) if last.2 == styles => last.1 = to,
+                _ => runs.push((from, to, styles)),
+            }
+        }
+        if runs.is_empty() {
+            runs.push((0, 0, Vec::new()));
+        }
+        runs.into_iter()
+            .map(|(from, to, styles)| StyledRun { text: &text[from..to], styles })
+            .collect()
+    }
+
+    /// Renders the trimmed text with properly nested styling markup: `open` and `close`
+    /// produce a style's tags and `escape` prepares the text between them.
+    pub fn render_styled(
+        &self,
+        open: impl Fn(TextStyle) -> String,
+        close: impl Fn(TextStyle) -> String,
+        escape: impl Fn(&str) -> String,
+    ) -> String {
+        let mut rendered = String::new();
+        let mut open_styles: Vec<TextStyle> = Vec::new();
+        for run in self.styled_runs() {
+            // Tags close in reverse order, so everything above a style that ends goes too
+            if let Some(first_ended) = open_styles.iter().position(|style| !run.styles.contains(style)) {
+                for style in open_styles.drain(first_ended..).rev() {
+                    rendered.push_str(&close(style));
+                }
+            }
+            for &style in &run.styles {
+                if !open_styles.contains(&style) {
+                    rendered.push_str(&open(style));
+                    open_styles.push(style);
+                }
+            }
+            rendered.push_str(&escape(run.text));
+        }
+        for style in open_styles.into_iter().rev() {
+            rendered.push_str(&close(style));
+        }
+        rendered
+    }
+
+    /// Adds `other`'s text on a new line, keeping the styling of both.
+    pub fn append_line(&mut self, other: &Segment) {
+        let other_text = other.text.trim();
+        if other_text.is_empty() {
+            return;
+        }
+        self.trim_text();
+        let base = if self.text.is_empty() { 0 } else { self.text.len() + 1 };
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.text.push_str(other_text);
+
+        let other_lead = other.text.len() - other.text.trim_start().len();
+        let shift = |offset: usize| base + offset.saturating_sub(other_lead).min(other_text.len());
+        self.styles.extend(
+            other
+                .styles
+                .iter()
+                .map(|span| StyleSpan { start: shift(span.start), end: shift(span.end), style: span.style })
+                .filter(|span| span.start < span.end),
+        );
+    }
+
+    /// Trims the text, moving the style ranges along with it.
+    fn trim_text(&mut self) {
+        let lead = self.text.len() - self.text.trim_start().len();
+        let length = self.text.trim().len();
+        self.text = self.text.trim().to_string();
+        for span in &mut self.styles {
+            span.start = span.start.saturating_sub(lead).min(length);
+            span.end = span.end.saturating_sub(lead).min(length);
+        }
+        self.styles.retain(|span| span.start < span.end);
+    }
+
+    /// Indices of the words after which the segment's text continues on a new line,
+    /// so word-timed payloads can keep the line breaks shaping put into `text`.
+    pub fn line_breaks(&self) -> Vec<usize> {
+        let mut line_ends = Vec::new();
+        let mut total = 0;
+        for line in self.text.trim().lines() {
+            total += line.split_whitespace().count();
+            line_ends.push(total);
+        }
+        // The last line ends with the cue
+        line_ends.pop();
+
+        let mut seen = 0;
+        let mut breaks = Vec::new();
+        for (index, word) in self.words.iter().enumerate() {
+            let count = word.text.split_whitespace().count();
+            seen += count;
+            if count > 0 && line_ends.contains(&seen) {
+                breaks.push(index);
+            }
+        }
+        breaks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start_ms: u64, end_ms: u64) -> Word {
+        Word {
+            text: text.to_string(),
+            start_ms,
+            end_ms,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn json_round_trips_and_omits_unset_fields() {
+        let mut transcript = Transcript::new(vec![Segment {
+            start_ms: 1200,
+            end_ms: 3450,
+            text: "Hello world.".to_string(),
+            confidence: Some(0.5),
+            speaker: Some("S1".to_string()),
+            styles: vec![StyleSpan { start: 0, end: 5, style: TextStyle::Italic }],
+            words: vec![Word { token_ids: vec![15947], ..word(" Hello", 1200, 1610) }, word(" world.", 1610, 3450)],
+            ..Default::default()
+        }]);
+        transcript.language = Some("en".to_string());
+        transcript.duration_ms = Some(754_200);
+
+        let json = serde_json::to_string_pretty(&transcript).unwrap();
+        assert!(json.contains("\"style\": \"italic\""));
+        for unset in ["language_probability", "task", "null", "token_ids\": []"] {
+            assert!(!json.contains(unset), "{}", unset);
+        }
+        let read = Transcript::from_json(&json).unwrap();
+        assert_eq!(read.schema_version, TRANSCRIPT_SCHEMA_VERSION);
+        assert_eq!((read.language.as_deref(), read.duration_ms), (Some("en"), Some(754_200)));
+        assert_eq!(read.segments, transcript.segments);
+    }
+
+    #[test]
+    fn unknown_schema_versions_are_rejected() {
+        for version in [0, TRANSCRIPT_SCHEMA_VERSION + 1] {
+            let json = format!(r#"{{ "schema_version": {}, "segments": [] }}"#, version);
+            let result = Transcript::from_json(&json);
+            assert!(result.is_err_and(|e| e.starts_with("Unsupported transcript schema version")), "{}", version);
+        }
+        assert!(Transcript::from_json(r#"{ "segments": [] }"#).is_err());
+        assert!(Transcript::from_json(r#"{ "schema_version": 1 }"#).is_ok_and(|t| t.segments.is_empty()));
+    }
+
+    #[test]
+    fn cues_skip_blank_segments() {
+        let segment = |text: &str| Segment { text: text.to_string(), ..Default::default() };
+        let transcript = Transcript::new(vec![segment("One"), segment(" \n"), segment(""), segment("Two")]);
+        let texts: Vec<&str> = transcript.cues().map(|cue| cue.text.as_str()).collect();
+        assert_eq!(texts, ["One", "Two"]);
+    }
+
+    #[test]
+    fn line_breaks_point_at_the_last_word_of_each_line() {
+        let segment = Segment {
+            text: "Hello there\nmy old\nfriend".to_string(),
+            words: vec![
+                word(" Hello", 0, 100),
+                word(" there", 100, 200),
+                word("", 200, 200),
+                word(" my old", 200, 400),
+                word(" friend", 400, 500),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(segment.line_breaks(), [1, 3]);
+
+        let single = Segment { text: " Hello there ".to_string(), ..segment.clone() };
+        assert!(single.line_breaks().is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::output::{prepare_output, OutputNaming};
+use crate::timing::format_timestamp;
+use crate::transcript::{Segment, TextStyle, Transcript};
+
+/// Frame rate assumed when the source has no video stream, the common broadcast rate in PAL regions.
+pub const DEFAULT_FRAME_RATE: f64 = 25.0;
+
+/// Which TTML profile a document is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtmlProfile {
+    /// W3C IMSC1 text profile (`.ttml`), timed in `HH:MM:SS:FF` frames.
+    Imsc1,
+    /// EBU-TT-D (`.ebu-tt-d.xml`); frames are not allowed, so times are frame-aligned `HH:MM:SS.mmm`.
+    EbuTtD,
+}
+
+impl TtmlProfile {
+    pub fn extension(self) -> &'static str {
+        match self {
+            TtmlProfile::Imsc1 => "ttml",
+            TtmlProfile::EbuTtD => "ebu-tt-d.xml",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TtmlProfile::Imsc1 => "IMSC1",
+            TtmlProfile::EbuTtD => "EBU-TT-D",
+        }
+    }
+}
+
+/// A TTML frame rate: an integer nominal rate plus the NTSC `1000/1001` multiplier where needed.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRate {
+    nominal: u32,
+    ntsc: bool,
+}
+
+impl FrameRate {
+    /// Maps a rate printed by ffmpeg (`23.98`, `29.97`, `25`, ...) onto a TTML frame rate.
+    pub fn from_fps(fps: f64) -> FrameRate {
+        let nominal = fps.round().max(1.0) as u32;
+        // 23.976, 29.97 and 59.94 sit about 0.1% below the next integer
+        let ntsc = (fps - nominal as f64 * 1000.0 / 1001.0).abs() < 0.01 && fps.fract() != 0.0;
+        FrameRate { nominal, ntsc }
+    }
+
+    fn effective(self) -> f64 {
+        if self.ntsc {
+            self.nominal as f64 * 1000.0 / 1001.0
+        } else {
+            self.nominal as f64
+        }
+    }
+
+    fn parameters(self) -> String {
+        let mut attributes = format!(" ttp:frameRate=\"{}\"", self.nominal);
+        if self.ntsc {
+            attributes.push_str(" ttp:frameRateMultiplier=\"1000 1001\"");
+        }
+        attributes
+    }
+
+    /// Splits a time into whole media seconds and the frame within that second.
+    fn snap(self, ms: u64) -> (u64, u32) {
+        let effective = self.effective();
+        let frames = (ms as f64 / 1000.0 * effective).round();
+        let seconds = (frames / effective).floor();
+        let frame = (frames - (seconds * effective).ceil()).max(0.0) as u32;
+        if frame >= self.nominal {
+            (seconds as u64 + 1, 0)
+        } else {
+            (seconds as u64, frame)
+        }
+    }
+}
+
+pub fn create_ttml(
+    transcript: &Transcript,
+    profile: TtmlProfile,
+    frame_rate: FrameRate,
+    naming: &OutputNaming,
+    app: AppHandle,
+) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, profile.extension(), &app) else {
+        return Ok(String::new());
+    };
+
+    let mut file = File::create(&file_path_buf)
+        .map_err(|e| format!("Failed to create {} file: {}", profile.label(), e))?;
+    file.write_all(render_ttml(transcript, profile, frame_rate).as_bytes())
+        .map_err(|e| format!("Failed to write to {} file: {}", profile.label(), e))?;
+
+    app.emit("subtitle_created", format!("{} Created :: {}", profile.label(), file_path_buf.display()))
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+
+    Ok(file_path_buf.to_string_lossy().to_string())
+}
+
+/// Renders a transcript's segments as a TTML document in the transcript's language.
+///
+/// Both profiles share one bottom region and a white-on-black style; only the
+/// namespaces, conformance metadata and time expressions differ.
+pub fn render_ttml(transcript: &Transcript, profile: TtmlProfile, frame_rate: FrameRate) -> String {
+    let language = escape_xml(transcript.language.as_deref().unwrap_or("und"));
+    let mut paragraphs = String::new();
+    let mut index = 0;
+
+    for segment in transcript.cues() {
+        let (begin, end) = match profile {
+            TtmlProfile::Imsc1 => (frame_time(segment.start_ms, frame_rate), frame_time(segment.end_ms, frame_rate)),
+            TtmlProfile::EbuTtD => (media_time(segment.start_ms, frame_rate), media_time(segment.end_ms, frame_rate)),
+        };
+        if begin == end {
+            // Shorter than a frame, a player could never show it
+            continue;
+        }
+
+        index += 1;
+        paragraphs.push_str(&match profile {
+            TtmlProfile::Imsc1 => format!(
+                "      <p xml:id=\"sub{}\" begin=\"{}\" end=\"{}\">{}</p>\n",
+                index, begin, end, text_spans(segment, "")
+            ),
+            TtmlProfile::EbuTtD => format!(
+                "      <tt:p xml:id=\"sub{}\" begin=\"{}\" end=\"{}\">{}</tt:p>\n",
+                index, begin, end, text_spans(segment, "tt:")
+            ),
+        });
+    }
+
+    match profile {
+        TtmlProfile::Imsc1 => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+             xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xmlns:ttm=\"http://www.w3.org/ns/ttml#metadata\" \
+             ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\" ttp:timeBase=\"media\"{} \
+             ttp:cellResolution=\"32 15\" xml:lang=\"{}\">\n\
+             \x20 <head>\n\
+             \x20   <styling>\n\
+             \x20     <style xml:id=\"s_default\" tts:fontFamily=\"proportionalSansSerif\" tts:fontSize=\"100%\" \
+             tts:lineHeight=\"125%\" tts:textAlign=\"center\"/>\n\
+             \x20     <style xml:id=\"s_text\" tts:color=\"#FFFFFF\" tts:backgroundColor=\"#000000C2\"/>\n\
+             \x20     <style xml:id=\"s_italic\" tts:fontStyle=\"italic\"/>\n\
+             \x20     <style xml:id=\"s_bold\" tts:fontWeight=\"bold\"/>\n\
+             \x20     <style xml:id=\"s_underline\" tts:textDecoration=\"underline\"/>\n\
+             \x20   </styling>\n\
+             \x20   <layout>\n\
+             \x20     <region xml:id=\"r_bottom\" tts:origin=\"10% 10%\" tts:extent=\"80% 80%\" tts:displayAlign=\"after\"/>\n\
+             \x20   </layout>\n\
+             \x20 </head>\n\
+             \x20 <body region=\"r_bottom\" style=\"s_default\">\n\
+             \x20   <div>\n\
+             {}\
+             \x20   </div>\n\
+             \x20 </body>\n\
+             </tt>\n",
+            frame_rate.parameters(),
+            language,
+            paragraphs
+        ),
+        TtmlProfile::EbuTtD => format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <tt:tt xmlns:tt=\"http://www.w3.org/ns/ttml\" xmlns:ttp=\"http://www.w3.org/ns/ttml#parameter\" \
+             xmlns:tts=\"http://www.w3.org/ns/ttml#styling\" xmlns:ebuttm=\"urn:ebu:tt:metadata\" \
+             xmlns:ebutts=\"urn:ebu:tt:style\" ttp:timeBase=\"media\" ttp:cellResolution=\"50 30\" xml:lang=\"{}\">\n\
+             \x20 <tt:head>\n\
+             \x20   <tt:metadata>\n\
+             \x20     <ebuttm:documentMetadata>\n\
+             \x20       <ebuttm:conformsToStandard>urn:ebu:tt:distribution:2018-04</ebuttm:conformsToStandard>\n\
+             \x20     </ebuttm:documentMetadata>\n\
+             \x20   </tt:metadata>\n\
+             \x20   <tt:styling>\n\
+             \x20     <tt:style xml:id=\"s_default\" tts:fontFamily=\"proportionalSansSerif\" tts:fontSize=\"100%\" \
+             tts:lineHeight=\"normal\" tts:textAlign=\"center\" ebutts:linePadding=\"0.5c\"/>\n\
+             \x20     <tt:style xml:id=\"s_text\" tts:color=\"#FFFFFF\" tts:backgroundColor=\"#000000\"/>\n\
+             \x20     <tt:style xml:id=\"s_italic\" tts:fontStyle=\"italic\"/>\n\
+             \x20     <tt:style xml:id=\"s_bold\" tts:fontWeight=\"bold\"/>\n\
+             \x20     <tt:style xml:id=\"s_underline\" tts:textDecoration=\"underline\"/>\n\
+             \x20   </tt:styling>\n\
+             \x20   <tt:layout>\n\
+             \x20     <tt:region xml:id=\"r_bottom\" tts:origin=\"10% 10%\" tts:extent=\"80% 80%\" \
+             tts:displayAlign=\"after\" tts:writingMode=\"lrtb\"/>\n\
+             \x20   </tt:layout>\n\
+             \x20 </tt:head>\n\
+             \x20 <tt:body>\n\
+             \x20   <tt:div region=\"r_bottom\" style=\"s_default\">\n\
+             {}\
+             \x20   </tt:div>\n\
+             \x20 </tt:body>\n\
+             </tt:tt>\n",
+            language,
+            paragraphs
+        ),
+    }
+}
+
+/// `HH:MM:SS:FF`, where the frame counts within the current media second.
+fn frame_time(ms: u64, frame_rate: FrameRate) -> String {
+    let (seconds, frame) = frame_rate.snap(ms);
+    format!("{:02}:{:02}:{:02}:{:02}", seconds / 3600, (seconds % 3600) / 60, seconds % 60, frame)
+}
+
+/// `HH:MM:SS.mmm`, aligned to the start of the nearest frame.
+fn media_time(ms: u64, frame_rate: FrameRate) -> String {
+    let effective = frame_rate.effective();
+    let snapped = (ms as f64 / 1000.0 * effective).round() / effective;
+    format_timestamp((snapped * 1000.0).round() as u64, '.')
+}
+
+/// The cue's text as sibling `s_text` spans, one per run of equal styling, referencing the
+/// `s_italic`, `s_bold` and `s_underline` styles; EBU-TT-D does not allow nested spans.
+fn text_spans(segment: &Segment, prefix: &str) -> String {
+    segment
+        .styled_runs()
+        .iter()
+        .map(|run| {
+            let styles: String = run.styles.iter().map(|style| format!(" s_{}", style_id(*style))).collect();
+            format!(
+                "<{prefix}span style=\"s_text{}\">{}</{prefix}span>",
+                styles,
+                escape_xml(run.text).replace('\n', &format!("<{}br/>", prefix)),
+                prefix = prefix
+            )
+        })
+        .collect()
+}
+
+fn style_id(style: TextStyle) -> &'static str {
+    match style {
+        TextStyle::Italic => "italic",
+        TextStyle::Bold => "bold",
+        TextStyle::Underline => "underline",
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::StyleSpan;
+    use crate::transcript::Segment;
+
+    fn transcript(cues: &[(u64, u64, &str)]) -> Transcript {
+        let mut transcript = Transcript::new(
+            cues.iter()
+                .map(|&(start_ms, end_ms, text)| Segment {
+                    start_ms,
+                    end_ms,
+                    text: text.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        transcript.language = Some("en".to_string());
+        transcript
+    }
+
+    /// Checks that every element is closed in order and every `&` starts an entity.
+    fn assert_well_formed(document: &str) {
+        let mut open = Vec::new();
+        let mut rest = document.strip_prefix("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n").expect("XML declaration");
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>').expect("unterminated tag");
+            let tag = &rest[start + 1..end];
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open.pop(), Some(name.to_string()), "mismatched </{}>", name);
+            } else if !tag.ends_with('/') {
+                open.push(tag.split_whitespace().next().unwrap().to_string());
+            }
+            rest = &rest[end + 1..];
+        }
+        assert!(open.is_empty(), "unclosed elements: {:?}", open);
+        for (index, _) in document.match_indices('&') {
+            let entity = &document[index..document[index..].find(';').map_or(document.len(), |end| index + end + 1)];
+            assert!(["&amp;", "&lt;", "&gt;", "&quot;"].contains(&entity), "bare ampersand in {:?}", entity);
+        }
+    }
+
+    #[test]
+    fn imsc1_document_is_well_formed_with_ntsc_frame_rate() {
+        let document = render_ttml(
+            &transcript(&[(1000, 2000, "Hello"), (2500, 4000, "two\nlines")]),
+            TtmlProfile::Imsc1,
+            FrameRate::from_fps(29.97),
+        );
+        assert_well_formed(&document);
+        assert!(document.contains("<tt xmlns=\"http://www.w3.org/ns/ttml\""));
+        assert!(document.contains("ttp:profile=\"http://www.w3.org/ns/ttml/profile/imsc1/text\""));
+        assert!(document.contains("ttp:frameRate=\"30\" ttp:frameRateMultiplier=\"1000 1001\""));
+        assert!(document.contains("xml:lang=\"en\""));
+        assert!(document.contains(
+            "<p xml:id=\"sub1\" begin=\"00:00:01:00\" end=\"00:00:02:00\"><span style=\"s_text\">Hello</span></p>"
+        ));
+        assert!(document.contains("two<br/>lines"));
+    }
+
+    #[test]
+    fn ebu_tt_d_document_is_well_formed_with_media_times() {
+        let document = render_ttml(
+            &transcript(&[(1000, 2010, "Hello"), (2500, 4000, "two\nlines")]),
+            TtmlProfile::EbuTtD,
+            FrameRate::from_fps(25.0),
+        );
+        assert_well_formed(&document);
+        assert!(document.contains("<tt:tt xmlns:tt=\"http://www.w3.org/ns/ttml\""));
+        assert!(document.contains("xmlns:ebuttm=\"urn:ebu:tt:metadata\""));
+        assert!(document.contains("urn:ebu:tt:distribution:2018-04"));
+        // Frames are not allowed in EBU-TT-D
+        assert!(!document.contains("ttp:frameRate"));
+        assert!(document.contains("begin=\"00:00:01.000\" end=\"00:00:02.000\""));
+        assert!(document.contains("two<tt:br/>lines"));
+    }
+
+    #[test]
+    fn integer_rates_have_no_multiplier() {
+        assert_eq!(FrameRate::from_fps(25.0).parameters(), " ttp:frameRate=\"25\"");
+        assert_eq!(
+            FrameRate::from_fps(23.976).parameters(),
+            " ttp:frameRate=\"24\" ttp:frameRateMultiplier=\"1000 1001\""
+        );
+    }
+
+    #[test]
+    fn snap_rolls_over_into_the_next_second() {
+        let pal = FrameRate::from_fps(25.0);
+        assert_eq!(pal.snap(959), (0, 24));
+        assert_eq!(pal.snap(999), (1, 0));
+        assert_eq!(pal.snap(1000), (1, 0));
+
+        let ntsc = FrameRate::from_fps(29.97);
+        assert_eq!(ntsc.snap(980), (0, 29));
+        assert_eq!(ntsc.snap(990), (1, 0));
+        assert_eq!(ntsc.snap(2000), (2, 0));
+        assert_eq!(frame_time(3_600_000, pal), "01:00:00:00");
+    }
+
+    #[test]
+    fn cues_shorter_than_a_frame_are_skipped() {
+        for profile in [TtmlProfile::Imsc1, TtmlProfile::EbuTtD] {
+            let document = render_ttml(
+                &transcript(&[(1000, 1010, "blink"), (2000, 3000, "kept")]),
+                profile,
+                FrameRate::from_fps(25.0),
+            );
+            assert!(!document.contains("blink"));
+            // Numbering has no gap where the short cue was
+            assert!(document.contains("xml:id=\"sub1\""));
+            assert!(!document.contains("xml:id=\"sub2\""));
+        }
+    }
+
+    #[test]
+    fn text_and_language_are_escaped() {
+        let mut source = transcript(&[(0, 1000, "Tom & Jerry <3 \"cats\"")]);
+        source.language = Some("en\"<".to_string());
+        for profile in [TtmlProfile::Imsc1, TtmlProfile::EbuTtD] {
+            let document = render_ttml(&source, profile, FrameRate::from_fps(25.0));
+            assert_well_formed(&document);
+            assert!(document.contains("Tom &amp; Jerry &lt;3 &quot;cats&quot;"));
+            assert!(document.contains("xml:lang=\"en&quot;&lt;\""));
+        }
+    }
+
+    #[test]
+    fn styling_becomes_sibling_spans() {
+        let mut source = transcript(&[(0, 1000, "plain bold both")]);
+        source.segments[0].styles = vec![
+            StyleSpan { start: 6, end: 15, style: TextStyle::Bold },
+            StyleSpan { start: 11, end: 15, style: TextStyle::Italic },
+        ];
+        let document = render_ttml(&source, TtmlProfile::EbuTtD, FrameRate::from_fps(25.0));
+        assert_well_formed(&document);
+        assert!(document.contains(
+            "<tt:span style=\"s_text\">plain </tt:span><tt:span style=\"s_text s_bold\">bold </tt:span>\
+             <tt:span style=\"s_text s_italic s_bold\">both</tt:span>"
+        ));
+        assert!(document.contains("<tt:style xml:id=\"s_italic\" tts:fontStyle=\"italic\"/>"));
+    }
+}
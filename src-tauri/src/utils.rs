@@ -1,6 +1,6 @@
 use rfd::FileDialog;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use futures::StreamExt;
 use std::env;
 use std::fs;
 use tauri::{AppHandle, Emitter, Manager};
@@ -10,22 +10,55 @@ use std::path::PathBuf;
 use std::fs::File;
 use std::io::Write;
 use std::error::Error;
-use hound::WavReader;
-use serde_json::json;
+use std::sync::OnceLock;
+
+use crate::download::{download_resumable, DownloadEvent};
+use crate::job::{JobHandle, CANCELLED_MESSAGE};
+use crate::output::{parse_output_formats, prepare_output, validate_naming_template, CollisionPolicy, OutputNaming};
+use crate::ass::AssStyle;
+use crate::lrc::LrcOptions;
+use crate::models::{partial_path, verified_path, ModelEntry};
+use crate::retime::RetimeOptions;
+use crate::shaping::ShapingOptions;
+use crate::timing::format_timestamp;
+use crate::transcript::Transcript;
+use crate::text_export::TranscriptOptions;
+use crate::vtt::VttOptions;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct SelectedData {
     model: Option<String>,
     file_path: Option<String>,
     folder_path: Option<String>,
+    language: Option<String>,
+    task: Option<String>,
+    ffmpeg_path: Option<String>,
+    naming_template: Option<String>,
+    collision_policy: Option<String>,
+    output_formats: Option<String>,
+    vtt_options: Option<VttOptions>,
+    ass_style: Option<AssStyle>,
+    transcript_options: Option<TranscriptOptions>,
+    lrc_options: Option<LrcOptions>,
+    shaping_options: Option<ShapingOptions>,
+    retime_options: Option<RetimeOptions>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct MetaData {
     key: String,
     value: String,
 }
 
+impl MetaData {
+    pub fn new(key: &str, value: &str) -> Self {
+        MetaData {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn select_folder() -> String {
     let result = FileDialog::new()
@@ -62,64 +95,184 @@ pub async fn select_file(is_model: bool) -> String {
     }
 }
 
-#[tauri::command]
-pub fn save_selection(data: Vec<MetaData>, app: AppHandle) -> Result<(), String> {
-    let temp_dir = env::temp_dir();
-    let srtify_dir = temp_dir.join("srtify");
-
-    // Create the srtify directory if it doesn't exist
-    fs::create_dir_all(&srtify_dir).expect("Failed to create srtify directory");
-    let file_path = srtify_dir.join("srtify.json");
-
-    // Load existing data or initialize with default
-    let mut selected_data: SelectedData = if file_path.exists() {
+impl SelectedData {
+    /// Reads the saved selections, or the defaults when nothing was saved yet.
+    pub fn load() -> Result<SelectedData, String> {
+        let file_path = selections_path();
+        if !file_path.exists() {
+            return Ok(SelectedData::default());
+        }
         let file_content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-        let mut data: SelectedData = serde_json::from_str(&file_content).map_err(|e| e.to_string())?;
+        serde_json::from_str(&file_content).map_err(|e| e.to_string())
+    }
 
-        if data.model.is_none() || data.model.as_deref() == Some("null") || data.model.as_deref() == Some("") {
-            data.model = Some("whisper".to_string());
+    /// Validates key/value pairs sent by the frontend and applies them.
+    pub fn apply(&mut self, data: Vec<MetaData>) -> Result<(), String> {
+        for entry in data {
+            match entry.key.as_str() {
+                "model" => {
+                    self.model = match entry.value.as_str() {
+                        "" | "null" | "none" => Some("whisper".to_string()),
+                        _ => Some(entry.value),
+                    };
+                }
+                "file" => {
+                    self.file_path = Some(entry.value);
+                }
+                "folder" => {
+                    self.folder_path = Some(entry.value);
+                }
+                "language" => {
+                    self.language = match entry.value.as_str() {
+                        "" | "null" | "none" => Some("en".to_string()),
+                        "auto" => Some(entry.value),
+                        code if whisper_rs::get_lang_id(code).is_some() => Some(entry.value),
+                        _ => return Err(format!("Unsupported language: {}", entry.value)),
+                    };
+                }
+                "task" => {
+                    self.task = match entry.value.as_str() {
+                        "" | "null" | "none" => Some("transcribe".to_string()),
+                        "transcribe" | "translate" => Some(entry.value),
+                        _ => return Err(format!("Unsupported task: {}", entry.value)),
+                    };
+                }
+                "ffmpeg" => {
+                    self.ffmpeg_path = match entry.value.trim() {
+                        "" | "null" | "none" => None,
+                        path => Some(path.to_string()),
+                    };
+                }
+                "naming" => {
+                    self.naming_template = match entry.value.trim() {
+                        "" | "null" | "none" => None,
+                        template => {
+                            validate_naming_template(template)?;
+                            Some(template.to_string())
+                        }
+                    };
+                }
+                "collision" => {
+                    self.collision_policy = match entry.value.as_str() {
+                        "" | "null" | "none" => None,
+                        policy if CollisionPolicy::parse(policy).is_some() => Some(entry.value),
+                        _ => return Err(format!("Unknown collision policy: {}", entry.value)),
+                    };
+                }
+                "formats" => {
+                    self.output_formats = Some(parse_output_formats(&entry.value)?.join(","));
+                }
+                "vtt" => {
+                    let options: VttOptions = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid WebVTT options: {}", e))?;
+                    options.validate()?;
+                    self.vtt_options = Some(options);
+                }
+                "ass" => {
+                    let style: AssStyle = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid ASS style: {}", e))?;
+                    style.validate()?;
+                    self.ass_style = Some(style);
+                }
+                "transcript" => {
+                    let options: TranscriptOptions = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid transcript options: {}", e))?;
+                    options.validate()?;
+                    self.transcript_options = Some(options);
+                }
+                "lrc" => {
+                    let options: LrcOptions = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid LRC options: {}", e))?;
+                    self.lrc_options = Some(options);
+                }
+                "shaping" => {
+                    let options: ShapingOptions = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid subtitle shaping options: {}", e))?;
+                    options.validate()?;
+                    self.shaping_options = Some(options);
+                }
+                "retime" => {
+                    let options: RetimeOptions = serde_json::from_str(&entry.value)
+                        .map_err(|e| format!("Invalid retiming options: {}", e))?;
+                    options.validate()?;
+                    self.retime_options = Some(options);
+                }
+                _ => {
+                    return Err(format!("Unknown key: {}", entry.key));
+                }
+            }
         }
 
-        data
-    } else {
-        SelectedData::default()
-    };
-
-    // Iterate through the array and update selected_data accordingly
-    for entry in data {
-        match entry.key.as_str() {
-        "model" => {
-                selected_data.model = match entry.value.as_str() {
-                    "" | "null" | "none" => Some("whisper".to_string()),
-                    _ => Some(entry.value),
-            };
-        }
-        "file" => {
-                selected_data.file_path = Some(entry.value);
-        }
-        "folder" => {
-                selected_data.folder_path = Some(entry.value);
+        // Ensure the model is not None, null, or empty after all updates
+        if self.model.is_none() || self.model.as_deref() == Some("null") || self.model.as_deref() == Some("") {
+            self.model = Some("whisper".to_string());
         }
-        _ => {
-                return Err(format!("Unknown key: {}", entry.key));
-            }
+        Ok(())
+    }
+
+    /// One selection as `load_selection` returns it; option structs come back as JSON.
+    pub fn get(&self, key: &str) -> Result<Option<String>, String> {
+        match key {
+            "model" => Ok(self.model.clone()),
+            "file" => Ok(self.file_path.clone()),
+            "folder" => Ok(self.folder_path.clone()),
+            "language" => Ok(self.language.clone()),
+            "task" => Ok(self.task.clone()),
+            "ffmpeg" => Ok(self.ffmpeg_path.clone()),
+            "naming" => Ok(self.naming_template.clone()),
+            "collision" => Ok(self.collision_policy.clone()),
+            "formats" => Ok(self.output_formats.clone()),
+            "vtt" => to_json(&self.vtt_options),
+            "ass" => to_json(&self.ass_style),
+            "transcript" => to_json(&self.transcript_options),
+            "lrc" => to_json(&self.lrc_options),
+            "shaping" => to_json(&self.shaping_options),
+            "retime" => to_json(&self.retime_options),
+            _ => Err(format!("Unknown key: {}", key)),
         }
     }
 
-    // Ensure the model is not None, null, or empty after all updates
-    if selected_data.model.is_none() || selected_data.model.as_deref() == Some("null") || selected_data.model.as_deref() == Some("") {
-        selected_data.model = Some("whisper".to_string());
+    /// Saves the selections for the next launch and returns them as JSON.
+    fn store(&self) -> Result<String, String> {
+        let file_path = selections_path();
+        let srtify_dir = file_path.parent().unwrap_or(Path::new("."));
+        fs::create_dir_all(srtify_dir).map_err(|e| format!("Failed to create srtify directory: {}", e))?;
+        let state_json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+
+        // Write through a per-process temp file and rename, so another instance never reads a half written file
+        let temp_path = srtify_dir.join(format!("srtify.json.{}.tmp", std::process::id()));
+        fs::write(&temp_path, &state_json).map_err(|e| e.to_string())?;
+        fs::rename(&temp_path, &file_path).map_err(|e| e.to_string())?;
+        Ok(state_json)
     }
+}
+
+/// Reads an option struct selection, falling back to its defaults when it is unset or unreadable.
+pub fn json_selection<T: DeserializeOwned + Default>(selections: &SelectedData, key: &str) -> T {
+    selections
+        .get(key)
+        .ok()
+        .flatten()
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default()
+}
+
+fn to_json<T: Serialize>(options: &Option<T>) -> Result<Option<String>, String> {
+    options.as_ref().map(serde_json::to_string).transpose().map_err(|e| e.to_string())
+}
 
-    // Serialize the updated data to JSON
-    let state_json = serde_json::to_string(&selected_data).map_err(|e| e.to_string())?;
-    let state_json_clone = state_json.clone();
+fn selections_path() -> PathBuf {
+    env::temp_dir().join("srtify").join("srtify.json")
+}
 
-    // Write the updated data to the file
-    fs::write(&file_path, state_json).map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn save_selection(data: Vec<MetaData>, app: AppHandle) -> Result<(), String> {
+    let mut selected_data = SelectedData::load()?;
+    selected_data.apply(data)?;
+    let state_json = selected_data.store()?;
 
     // Emit the updated data to the frontend
-    app.emit("info", state_json_clone).unwrap_or_else(|e| {
+    app.emit("info", state_json).unwrap_or_else(|e| {
         eprintln!("Emit error: {}", e);
     });
 
@@ -128,27 +281,19 @@ pub fn save_selection(data: Vec<MetaData>, app: AppHandle) -> Result<(), String>
 
 #[tauri::command]
 pub fn load_selection(key: String) -> Result<Option<String>, String> {
-    let temp_dir = env::temp_dir();
-    let srtify_dir = temp_dir.join("srtify");
-
-    fs::create_dir_all(&srtify_dir).expect("Failed to create srtify directory");
-    let file_path = srtify_dir.join("srtify.json");
-
-    let selected_data: SelectedData = if file_path.exists() {
-        let file_content = fs::read_to_string(&file_path).map_err(|e| e.to_string())?;
-        serde_json::from_str(&file_content).map_err(|e| e.to_string())?
-    } else {
-        return Ok(None);
-    };
+    SelectedData::load()?.get(&key)
+}
 
-    match key.as_str() {
-        "model" => Ok(selected_data.model.clone()),
-        "file" => Ok(selected_data.file_path.clone()),
-        "folder" => Ok(selected_data.folder_path.clone()),
-        _ => Err(format!("Unknown key: {}", key)),
-    }
+/// Shows a failure in the UI; the caller still returns the error itself.
+pub fn emit_error(app: &AppHandle, message: &str) {
+    app.emit("error", message).unwrap_or_else(|e| {
+        eprintln!("Emit error: {}", e);
+    });
 }
 
+/// Environment variable that can point at a specific ffmpeg binary.
+const FFMPEG_ENV_VAR: &str = "SRTIFY_FFMPEG";
+
 fn get_ffmpeg_path(app: tauri::AppHandle) -> Result<PathBuf, String> {
     let target_os = std::env::consts::OS;
     let path = match target_os {
@@ -161,7 +306,6 @@ fn get_ffmpeg_path(app: tauri::AppHandle) -> Result<PathBuf, String> {
         .resolve(path, tauri::path::BaseDirectory::Resource)
         .map_err(|e| format!("Failed to resolve FFmpeg path: {}", e))?;
 
-    println!("resource_path:: {:?}", resource_path);
     if !resource_path.exists() {
         return Err(format!("FFmpeg not found at {:?}", resource_path));
     }
@@ -169,38 +313,275 @@ fn get_ffmpeg_path(app: tauri::AppHandle) -> Result<PathBuf, String> {
     Ok(resource_path)
 }
 
-pub fn extract_audio(video_path: &str, app: tauri::AppHandle) -> Result<String, String> {
-    let ffmpeg_path = get_ffmpeg_path(app).expect("Failed to get FFmpeg path");
+/// Finds ffmpeg the first time a job needs it and hands the same binary to every later
+/// step, so candidates are probed and reported only once per job.
+pub struct FfmpegLocator {
+    app: tauri::AppHandle,
+    configured: Option<String>,
+    resolved: OnceLock<Result<PathBuf, String>>,
+}
 
-    if !ffmpeg_path.exists() {
-        return Err(format!("FFmpeg binary not found at {:?}", ffmpeg_path).to_string());
+impl FfmpegLocator {
+    /// `configured` is the path picked in the UI, tried before every other candidate.
+    pub fn new(app: &tauri::AppHandle, configured: Option<String>) -> Self {
+        FfmpegLocator {
+            app: app.clone(),
+            configured,
+            resolved: OnceLock::new(),
+        }
     }
-    println!("ffmpeg_path {:?}", ffmpeg_path);
-    let temp_dir = env::temp_dir();
-    let srtify_dir = temp_dir.join("srtify");
-    fs::create_dir_all(&srtify_dir).expect("Failed to create srtify directory");
-    let audio_output = srtify_dir.join("output.wav");
 
-    if let Err(e) = fs::remove_file(&audio_output) {
-        eprintln!("Failed to remove existing audio output file: {}", e);
+    pub fn path(&self) -> Result<&Path, String> {
+        self.resolved
+            .get_or_init(|| find_ffmpeg(&self.app, self.configured.as_deref()))
+            .as_deref()
+            .map_err(Clone::clone)
     }
+}
 
-    Command::new(ffmpeg_path)
-        .args(&[
-            "-y",
-            "-i", video_path,
-            "-vn",
-            "-acodec", "pcm_s16le",
-            "-ar", "16000",
-            "-ac", "1",
-            audio_output.to_str().unwrap(),
-        ])
-        .stdout(Stdio::null())  // Suppress standard output
-        .stderr(Stdio::null())  // Suppress standard error
+/// Picks the first working ffmpeg from, in order: the path configured in the UI,
+/// `SRTIFY_FFMPEG`, the bundled resource and finally `PATH`.
+fn find_ffmpeg(app: &tauri::AppHandle, configured: Option<&str>) -> Result<PathBuf, String> {
+    let binary_name = if cfg!(windows) { "ffmpeg.exe" } else { "ffmpeg" };
+    let mut candidates: Vec<(PathBuf, &str)> = Vec::new();
+
+    if let Some(configured) = configured {
+        candidates.push((PathBuf::from(configured), "configured path"));
+    }
+    if let Some(from_env) = env::var_os(FFMPEG_ENV_VAR) {
+        candidates.push((PathBuf::from(from_env), FFMPEG_ENV_VAR));
+    }
+    if let Ok(bundled) = get_ffmpeg_path(app.clone()) {
+        candidates.push((bundled, "bundled resources"));
+    }
+    if let Some(search_path) = env::var_os("PATH") {
+        for dir in env::split_paths(&search_path) {
+            let candidate = dir.join(binary_name);
+            if candidate.is_file() {
+                candidates.push((candidate, "PATH"));
+            }
+        }
+    }
+
+    let mut rejected = Vec::new();
+    for (path, source) in candidates {
+        match ffmpeg_version(&path) {
+            Ok(version) => {
+                app.emit("info", format!("Using ffmpeg from {}: {} ({})", source, path.display(), version))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Emit error: {}", e);
+                    });
+                return Ok(path);
+            }
+            Err(e) => rejected.push(format!("{} ({}): {}", path.display(), source, e)),
+        }
+    }
+
+    if rejected.is_empty() {
+        Err(format!(
+            "FFmpeg not found: set its location in the app, the {} environment variable, or install it on PATH",
+            FFMPEG_ENV_VAR
+        ))
+    } else {
+        Err(format!("No working FFmpeg found: {}", rejected.join("; ")))
+    }
+}
+
+/// Runs `ffmpeg -version` and returns the first line of its banner.
+fn ffmpeg_version(path: &Path) -> Result<String, String> {
+    let output = Command::new(path)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
         .output()
-        .expect("Failed to extract audio");
+        .map_err(|e| e.to_string())?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let banner = stdout.lines().next().unwrap_or_default();
+    if !output.status.success() || !banner.starts_with("ffmpeg version") {
+        return Err("not an ffmpeg binary".to_string());
+    }
+
+    Ok(banner.trim_start_matches("ffmpeg version").trim().split(' ').next().unwrap_or_default().to_string())
+}
+
+/// Why an ffmpeg extraction failed, sent to the frontend as the `error` event payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FfmpegErrorKind {
+    NotFound,
+    MissingAudioStream,
+    UnsupportedCodec,
+    PermissionDenied,
+    Cancelled,
+    Failed,
+}
 
-    Ok(audio_output.to_str().unwrap().to_string())
+#[derive(Debug, Clone, Serialize)]
+pub struct FfmpegError {
+    pub kind: FfmpegErrorKind,
+    pub message: String,
+    pub exit_code: Option<i32>,
+    pub stderr: String,
+}
+
+impl FfmpegError {
+    fn new(kind: FfmpegErrorKind, message: impl Into<String>) -> Self {
+        FfmpegError {
+            kind,
+            message: message.into(),
+            exit_code: None,
+            stderr: String::new(),
+        }
+    }
+
+    /// Maps ffmpeg's exit code and stderr to the most specific failure we recognise.
+    fn from_output(exit_code: Option<i32>, stderr: String) -> Self {
+        let lowered = stderr.to_lowercase();
+        let (kind, message) = if lowered.contains("permission denied") {
+            (FfmpegErrorKind::PermissionDenied, "FFmpeg was denied access to the input or output file")
+        } else if lowered.contains("does not contain any stream")
+            || lowered.contains("matches no streams")
+            || lowered.contains("output file does not contain any stream")
+        {
+            (FfmpegErrorKind::MissingAudioStream, "The media file has no audio stream")
+        } else if lowered.contains("invalid data found when processing input")
+            || lowered.contains("decoder not found")
+            || lowered.contains("unknown decoder")
+            || lowered.contains("unsupported codec")
+            || lowered.contains("could not find codec parameters")
+        {
+            (FfmpegErrorKind::UnsupportedCodec, "FFmpeg could not decode the media file")
+        } else {
+            (FfmpegErrorKind::Failed, "FFmpeg failed to extract audio")
+        };
+
+        let message = match exit_code {
+            Some(code) => format!("{} (exit code {})", message, code),
+            None => format!("{} (terminated by signal)", message),
+        };
+
+        FfmpegError {
+            kind,
+            message,
+            exit_code,
+            stderr: tail_lines(&stderr, 20),
+        }
+    }
+}
+
+impl std::fmt::Display for FfmpegError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for FfmpegError {}
+
+fn tail_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
+pub fn extract_audio(video_path: &str, ffmpeg: &FfmpegLocator, job: &JobHandle) -> Result<String, FfmpegError> {
+    let ffmpeg_path = ffmpeg.path().map_err(|e| FfmpegError::new(FfmpegErrorKind::NotFound, e))?;
+    let audio_output = job.workspace().join("audio.wav");
+
+    job.track_file(&audio_output);
+
+    let mut child = Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-loglevel", "error", "-nostdin", "-y", "-i"])
+        .arg(video_path)
+        .args(["-vn", "-acodec", "pcm_s16le", "-ar", "16000", "-ac", "1"])
+        .arg(&audio_output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())  // Suppress standard output
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let kind = if e.kind() == std::io::ErrorKind::PermissionDenied {
+                FfmpegErrorKind::PermissionDenied
+            } else {
+                FfmpegErrorKind::Failed
+            };
+            FfmpegError::new(kind, format!("Failed to start FFmpeg: {}", e))
+        })?;
+
+    // Drain stderr on its own thread so a chatty ffmpeg never blocks on a full pipe
+    let stderr_pipe = child.stderr.take();
+    let stderr_reader = std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = stderr_pipe {
+            std::io::Read::read_to_end(&mut pipe, &mut buffer).ok();
+        }
+        String::from_utf8_lossy(&buffer).to_string()
+    });
+
+    // Poll ffmpeg so a stop request can kill it mid-extraction
+    let status = job.wait_for_child(child);
+    let stderr = stderr_reader.join().unwrap_or_default();
+
+    match status {
+        Ok(Some(status)) if status.success() => {}
+        Ok(Some(status)) => return Err(FfmpegError::from_output(status.code(), stderr)),
+        Ok(None) => return Err(FfmpegError::new(FfmpegErrorKind::Cancelled, CANCELLED_MESSAGE)),
+        Err(e) => {
+            return Err(FfmpegError::new(FfmpegErrorKind::Failed, format!("Failed to wait for FFmpeg: {}", e)))
+        }
+    }
+
+    // ffmpeg exits 0 for some inputs without writing a single audio sample
+    let written = fs::metadata(&audio_output).map(|m| m.len()).unwrap_or(0);
+    if written <= 44 {
+        let mut error = FfmpegError::new(FfmpegErrorKind::MissingAudioStream, "The media file has no audio stream");
+        error.exit_code = Some(0);
+        error.stderr = tail_lines(&stderr, 20);
+        return Err(error);
+    }
+
+    Ok(audio_output.to_string_lossy().to_string())
+}
+
+/// Frame size and rate of a media file's first video stream.
+#[derive(Debug, Clone, Copy)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Average frame rate as printed by ffmpeg, e.g. `25` or `29.97`.
+    pub frame_rate: Option<f64>,
+}
+
+/// Reads the first video stream from ffmpeg's input summary.
+///
+/// Returns `None` for audio only media or when ffmpeg is unavailable.
+pub fn probe_video(media_path: &str, ffmpeg: &FfmpegLocator) -> Option<VideoInfo> {
+    let ffmpeg_path = ffmpeg.path().ok()?;
+    // Without an output ffmpeg exits with an error, but still prints the stream summary
+    let output = Command::new(ffmpeg_path)
+        .args(["-hide_banner", "-nostdin", "-i"])
+        .arg(media_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .ok()?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stream = stderr
+        .lines()
+        .find(|line| line.trim_start().starts_with("Stream #") && line.contains("Video:"))?;
+
+    let (width, height) = stream.split([',', ' ']).find_map(|token| {
+        // Codec tags such as `0x31637661` also contain an `x`, but never start with a non-zero digit
+        let (width, height) = token.split_once('x')?;
+        let (width, height) = (width.parse::<u32>().ok()?, height.parse::<u32>().ok()?);
+        (!token.starts_with('0') && height > 0).then_some((width, height))
+    })?;
+    let frame_rate = stream
+        .split(',')
+        .find_map(|part| part.trim().strip_suffix(" fps")?.parse::<f64>().ok())
+        .filter(|fps| *fps > 0.0);
+
+    Some(VideoInfo { width, height, frame_rate })
 }
 
 pub fn is_video_or_audio(file_path: &str) -> Option<&'static str> {
@@ -222,38 +603,20 @@ pub fn is_video_or_audio(file_path: &str) -> Option<&'static str> {
     }
 }
 
-fn sec_to_time_format(sec: f64) -> String {
-    let hours = (sec / 3600.0).floor() as u32;
-    let minutes = ((sec % 3600.0) / 60.0).floor() as u32;
-    let seconds = (sec % 60.0).floor() as u32;
-    let milliseconds = ((sec - sec.floor()) * 1000.0).round() as u32;
-
-    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, milliseconds)
-}
-
-pub fn create_srt(subtitles: Vec<(String, f64, f64)>, app: tauri::AppHandle) -> Result<String, Box<dyn Error>> {
-    let folder_res = load_selection("folder".to_string());
-
-    let folder = match folder_res {
-        Ok(Some(m)) => m,
-        Ok(None) => return Err("Media file not found".into()),
-        Err(e) => return Err(format!("Error loading model: {}", e).into()),
+/// Writes SRT cues; times are whole milliseconds so a cue can never render as `,1000`.
+pub fn create_srt(transcript: &Transcript, naming: &OutputNaming, app: tauri::AppHandle) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, "srt", &app) else {
+        return Ok(String::new());
     };
-    let file_path_buf = Path::new(&folder).join("output.srt");
 
     if let Err(e) = fs::remove_file(&file_path_buf) {
         eprintln!("Failed to remove existing audio output file: {}", e);
     }
 
     let mut file = File::create(&file_path_buf).map_err(|e| format!("Failed to create SRT file: {}", e))?;
+    file.write_all(render_srt(transcript).as_bytes())
+        .map_err(|e| format!("Failed to write to SRT file: {}", e))?;
 
-    for (i, (text, start_sec, end_sec)) in subtitles.iter().enumerate() {
-        let start_time = sec_to_time_format(*start_sec);
-        let end_time = sec_to_time_format(*end_sec);
-
-        writeln!(file, "{}\n{} --> {}\n{}\n", i + 1, start_time, end_time, text)
-            .map_err(|e| format!("Failed to write to SRT file: {}", e))?;
-    }
     app.emit("subtitle_created", format!("Subtitle Created :: {}", file_path_buf.to_str().unwrap().to_string())).unwrap_or_else(|e| {
         eprintln!("Emit error: {}", e);
     });
@@ -263,25 +626,34 @@ pub fn create_srt(subtitles: Vec<(String, f64, f64)>, app: tauri::AppHandle) ->
     Ok(file_path_buf.to_str().unwrap().to_string())
 }
 
-pub fn create_json(segments: Vec<serde_json::Value>, app: tauri::AppHandle) -> Result<String, Box<dyn Error>> {
-    let folder_res = load_selection("folder".to_string());
+/// Renders a transcript's segments as numbered SRT cues.
+pub fn render_srt(transcript: &Transcript) -> String {
+    let mut srt = String::new();
+    for (i, segment) in transcript.cues().enumerate() {
+        let start_time = format_timestamp(segment.start_ms, ',');
+        let end_time = format_timestamp(segment.end_ms, ',');
+        let text = segment.render_styled(
+            |style| format!("<{}>", style.code()),
+            |style| format!("</{}>", style.code()),
+            str::to_string,
+        );
+        srt.push_str(&format!("{}\n{} --> {}\n{}\n\n", i + 1, start_time, end_time, text));
+    }
+    srt
+}
 
-    let folder = match folder_res {
-        Ok(Some(m)) => m,
-        Ok(None) => return Err("Output folder not found".into()),
-        Err(e) => return Err(format!("Error loading output folder: {}", e).into()),
+/// Writes the full transcript in the versioned schema documented on [`Transcript`].
+pub fn create_json(transcript: &Transcript, naming: &OutputNaming, app: tauri::AppHandle) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, "words.json", &app) else {
+        return Ok(String::new());
     };
 
-    let file_path_buf = Path::new(&folder).join("output.json");
-
     if let Err(e) = fs::remove_file(&file_path_buf) {
         eprintln!("Failed to remove existing JSON file: {}", e);
     }
 
-    let json_data = json!({ "segments": segments });
-
     let mut file = File::create(&file_path_buf).map_err(|e| format!("Failed to create JSON file: {}", e))?;
-    file.write_all(serde_json::to_string_pretty(&json_data)?.as_bytes())?;
+    file.write_all(serde_json::to_string_pretty(transcript)?.as_bytes())?;
 
     app.emit("subtitle_created", format!("JSON Created :: {}", file_path_buf.to_str().unwrap().to_string())).unwrap_or_else(|e| {
         eprintln!("Emit error: {}", e);
@@ -293,81 +665,91 @@ pub fn create_json(segments: Vec<serde_json::Value>, app: tauri::AppHandle) -> R
     Ok(file_path_buf.to_str().unwrap().to_string())
 }
 
-pub async fn download_model(url: &str, model_path: &str, app: AppHandle) -> Result<PathBuf, Box<dyn Error>> {
-    if Path::new(&model_path).exists() {
-        return Ok(PathBuf::from(model_path));
+/// Downloads a registry model to `model_path` unless an intact copy is already there.
+///
+/// Bytes go to `<model>.part`, resuming an earlier interrupted download when the server
+/// allows it; only when the SHA-256 (and size, where known) match the registry and the
+/// file is a ggml model is it renamed into place, so an interrupted or corrupt download
+/// is never picked up as a model. Models without a pinned SHA-256 are not downloaded.
+pub async fn download_model(entry: &ModelEntry, model_path: &Path, app: AppHandle, job: &JobHandle) -> Result<PathBuf, Box<dyn Error>> {
+    if model_path.exists() {
+        match entry.check_cached(model_path) {
+            Ok(()) => return Ok(model_path.to_path_buf()),
+            Err(e) => {
+                app.emit("info", format!("Removing unverified model, downloading it again: {}", e)).unwrap_or_else(|e| {
+                    eprintln!("Emit error: {}", e);
+                });
+                fs::remove_file(model_path)?;
+                fs::remove_file(verified_path(model_path)).ok();
+            }
+        }
+    }
+    // Refuse before spending the bandwidth on a file that could not be accepted anyway
+    entry
+        .require_checksum()
+        .map_err(|e| format!("Cannot download {}: {}", entry.name, e))?;
+    if let Some(parent) = model_path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    let model = model_path.to_string_lossy().to_string();
+    let part_path = partial_path(model_path);
 
     // Emit download start event
     let start_event = serde_json::json!({
         "status": "download_start",
-        "model": model_path,
+        "model": model,
         "progress": 0.0
     });
     app.emit("download_progress", start_event).unwrap_or_else(|e| {
         eprintln!("Emit error: {}", e);
     });
 
-    let response = reqwest::get(url).await?;
-    
-    if !response.status().is_success() {
-        return Err(format!("Failed to download model from URL: {}", url).into());
-    }
-
-    // Create parent directories if needed
-    if let Some(parent) = Path::new(&model_path).parent() {
-        fs::create_dir_all(parent)?;
-    }
-
-    let mut file = fs::File::create(model_path.clone())?;
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded = 0;
-    let mut stream = response.bytes_stream();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        downloaded += chunk.len() as u64;
-        file.write_all(&chunk)?;
-        
-        // Calculate progress percentage if we know total size
-        let progress = if total_size > 0 {
-            (downloaded as f64 / total_size as f64) * 100.0
-        } else {
-            // Use -1 to indicate unknown total size
-            -1.0
-        };
+    let downloaded = download_resumable(&entry.url, &part_path, job, |event| match event {
+        DownloadEvent::Progress { downloaded, total } => {
+            // -1 signals an unknown total size
+            let progress = total
+                .filter(|&total| total > 0)
+                .map_or(-1.0, |total| downloaded as f64 / total as f64 * 100.0);
+            let progress_event = serde_json::json!({
+                "status": "download_progress",
+                "model": model,
+                "progress": progress,
+                "downloaded": downloaded,
+                "total_size": total.unwrap_or(0)
+            });
+            app.emit("download_progress", progress_event).unwrap_or_else(|e| {
+                eprintln!("Emit error: {}", e);
+            });
+        }
+        DownloadEvent::Resumed { from } => {
+            app.emit("info", format!("Resuming {} download at {} MB", entry.name, from / 1_000_000))
+                .unwrap_or_else(|e| {
+                    eprintln!("Emit error: {}", e);
+                });
+        }
+        DownloadEvent::Retrying { attempt, delay, error } => {
+            app.emit("info", format!("{}; retrying in {} s (attempt {})", error, delay.as_secs(), attempt + 1))
+                .unwrap_or_else(|e| {
+                    eprintln!("Emit error: {}", e);
+                });
+        }
+    })
+    .await?;
 
-        // Emit progress update
-        let progress_event = serde_json::json!({
-            "status": "download_progress",
-            "model": model_path,
-            "progress": progress,
-            "downloaded": downloaded,
-            "total_size": total_size
-        });
-        app.emit("download_progress", progress_event).unwrap_or_else(|e| {
-            eprintln!("Emit error: {}", e);
-        });
-    }
+    entry
+        .install_download(&part_path, model_path, &downloaded)
+        .map_err(|e| format!("Downloaded {} was rejected and deleted: {}", entry.name, e))?;
 
     // Emit completion event
     let complete_event = serde_json::json!({
         "status": "download_complete",
-        "model": model_path,
-        "path": model_path,
+        "model": model,
+        "path": model,
         "progress": 100
     });
     app.emit("download_complete", complete_event).unwrap_or_else(|e| {
         eprintln!("Emit error: {}", e);
     });
 
-    Ok(PathBuf::from(model_path))
+    Ok(model_path.to_path_buf())
 }
-
-pub fn get_audio_duration(file_path: &str) -> Result<f64, hound::Error> {
-    let reader = WavReader::open(file_path)?;
-    let spec = reader.spec();
-    
-    let duration = reader.duration() as f64 / spec.sample_rate as f64;
-    Ok(duration)
-}
\ No newline at end of file
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::Write;
+use tauri::{AppHandle, Emitter};
+
+use crate::output::{prepare_output, OutputNaming};
+use crate::timing::format_timestamp;
+use crate::transcript::{Segment, Transcript};
+
+/// WebVTT specific output settings, persisted as the `vtt` selection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VttOptions {
+    /// Cue box position, e.g. `50%` or `10%,line-left`.
+    pub position: Option<String>,
+    /// Line offset, e.g. `-2` (lines from the bottom) or `85%,end`.
+    pub line: Option<String>,
+    /// Text alignment: `start`, `center`, `end`, `left` or `right`.
+    pub align: Option<String>,
+    /// Wrap every word in `<c>` tags preceded by its start timestamp.
+    pub word_timestamps: bool,
+}
+
+impl VttOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(position) = non_empty(&self.position) {
+            let (value, anchor) = split_setting(position);
+            if !is_percentage(value) || !matches!(anchor, None | Some("line-left" | "center" | "line-right")) {
+                return Err(format!("Invalid WebVTT position: {}", position));
+            }
+        }
+        if let Some(line) = non_empty(&self.line) {
+            let (value, anchor) = split_setting(line);
+            let valid_value = is_percentage(value) || value.parse::<i32>().is_ok();
+            if !valid_value || !matches!(anchor, None | Some("start" | "center" | "end")) {
+                return Err(format!("Invalid WebVTT line: {}", line));
+            }
+        }
+        if let Some(align) = non_empty(&self.align) {
+            if !matches!(align, "start" | "center" | "end" | "left" | "right") {
+                return Err(format!("Invalid WebVTT align: {}", align));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cue settings appended after the timing line, including the leading space.
+    fn cue_settings(&self) -> String {
+        let mut settings = String::new();
+        if let Some(position) = non_empty(&self.position) {
+            settings.push_str(&format!(" position:{}", position));
+        }
+        if let Some(line) = non_empty(&self.line) {
+            settings.push_str(&format!(" line:{}", line));
+        }
+        if let Some(align) = non_empty(&self.align) {
+            settings.push_str(&format!(" align:{}", align));
+        }
+        settings
+    }
+}
+
+pub fn create_vtt(
+    transcript: &Transcript,
+    options: &VttOptions,
+    naming: &OutputNaming,
+    app: AppHandle,
+) -> Result<String, Box<dyn Error>> {
+    let Some(file_path_buf) = prepare_output(naming, "vtt", &app) else {
+        return Ok(String::new());
+    };
+
+    let mut file = File::create(&file_path_buf).map_err(|e| format!("Failed to create WebVTT file: {}", e))?;
+    file.write_all(render_vtt(transcript, options).as_bytes())
+        .map_err(|e| format!("Failed to write to WebVTT file: {}", e))?;
+
+    app.emit("subtitle_created", format!("WebVTT Created :: {}", file_path_buf.display()))
+        .unwrap_or_else(|e| {
+            eprintln!("Emit error: {}", e);
+        });
+
+    Ok(file_path_buf.to_string_lossy().to_string())
+}
+
+/// Renders a transcript's segments as WebVTT cues, with a `<v>` voice tag for segments that have a speaker.
+pub fn render_vtt(transcript: &Transcript, options: &VttOptions) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    let cue_settings = options.cue_settings();
+
+    for (index, segment) in transcript.cues().enumerate() {
+        let payload = if options.word_timestamps && !segment.words.is_empty() {
+            word_payload(segment)
+        } else {
+            segment.render_styled(
+                |style| format!("<{}>", style.code()),
+                |style| format!("</{}>", style.code()),
+                escape_text,
+            )
+        };
+        let voice = segment
+            .speaker
+            .as_deref()
+            .map(|speaker| format!("<v {}>", escape_text(speaker.trim())))
+            .unwrap_or_default();
+
+        vtt.push_str(&format!(
+            "{}\n{} --> {}{}\n{}{}\n\n",
+            index + 1,
+            format_timestamp(segment.start_ms, '.'),
+            format_timestamp(segment.end_ms, '.'),
+            cue_settings,
+            voice,
+            payload
+        ));
+    }
+
+    vtt
+}
+
+/// Builds `<c>word</c> <00:00:01.200><c>word</c>` karaoke style payloads, keeping the
+/// cue's line breaks between words.
+///
+/// Timestamp tags must fall strictly inside the cue, so the first word (and any
+/// word whisper placed on the cue boundary) is written without one.
+fn word_payload(segment: &Segment) -> String {
+    let breaks = segment.line_breaks();
+    let mut payload = String::new();
+    let mut previous = None;
+
+    for (index, word) in segment.words.iter().enumerate() {
+        let text = word.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(previous) = previous {
+            payload.push(if breaks.contains(&previous) { '\n' } else { ' ' });
+        }
+        if word.start_ms > segment.start_ms && word.start_ms < segment.end_ms {
+            payload.push_str(&format!("<{}>", format_timestamp(word.start_ms, '.')));
+        }
+        payload.push_str(&format!("<c>{}</c>", escape_text(text)));
+        previous = Some(index);
+    }
+
+    payload
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn split_setting(setting: &str) -> (&str, Option<&str>) {
+    match setting.split_once(',') {
+        Some((value, anchor)) => (value, Some(anchor)),
+        None => (setting, None),
+    }
+}
+
+fn is_percentage(value: &str) -> bool {
+    value
+        .strip_suffix('%')
+        .and_then(|number| number.parse::<f64>().ok())
+        .is_some_and(|number| (0.0..=100.0).contains(&number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{StyleSpan, TextStyle, Word};
+
+    fn word(text: &str, start_ms: u64, end_ms: u64) -> Word {
+        Word { text: text.to_string(), start_ms, end_ms, ..Default::default() }
+    }
+
+    #[test]
+    fn word_payload_keeps_line_breaks() {
+        let segment = Segment {
+            start_ms: 1000,
+            end_ms: 3000,
+            text: "Hello there\nworld".to_string(),
+            words: vec![word("Hello", 1000, 1400), word(" there", 1500, 1900), word("world", 2000, 2500)],
+            ..Default::default()
+        };
+        assert_eq!(
+            word_payload(&segment),
+            "<c>Hello</c> <00:00:01.500><c>there</c>\n<00:00:02.000><c>world</c>"
+        );
+    }
+
+    #[test]
+    fn styling_becomes_tags() {
+        let segment = Segment {
+            start_ms: 1000,
+            end_ms: 2000,
+            text: "a < b\nbold".to_string(),
+            styles: vec![
+                StyleSpan { start: 0, end: 5, style: TextStyle::Italic },
+                StyleSpan { start: 6, end: 10, style: TextStyle::Bold },
+            ],
+            words: vec![word("a", 1000, 1200)],
+            ..Default::default()
+        };
+        let transcript = Transcript::new(vec![segment]);
+        let vtt = render_vtt(&transcript, &VttOptions::default());
+        assert!(vtt.contains("00:00:01.000 --> 00:00:02.000\n<i>a &lt; b</i>\n<b>bold</b>\n"), "{}", vtt);
+    }
+}
@@ -3,12 +3,40 @@ const listen = window.__TAURI__.event.listen;
 
 const consoleElement = document.getElementById("console");
 const generateSubtitleButton = document.getElementById("generateSubtitle");
+const stopTranscriptionButton = document.getElementById("stopTranscription");
 const progressBar = generateSubtitleButton.querySelector('.progress-bar');
 const progressContainer = generateSubtitleButton.querySelector('.progress-container');
 const buttonTextElement = generateSubtitleButton.querySelector('.button-text');
 const modelInput = document.getElementById("model");
 const mediaFileInput = document.getElementById("mediaFile");
 const outputDirInput = document.getElementById("outputDir");
+const outputFormatInputs = document.querySelectorAll(".output-format");
+const vttWordTimestampsInput = document.getElementById("vttWordTimestamps");
+const vttAlignSelect = document.getElementById("vttAlign");
+const vttPositionInput = document.getElementById("vttPosition");
+const vttLineInput = document.getElementById("vttLine");
+const assKaraokeInput = document.getElementById("assKaraoke");
+const assFontNameInput = document.getElementById("assFontName");
+const assFontSizeInput = document.getElementById("assFontSize");
+const assPrimaryColorInput = document.getElementById("assPrimaryColor");
+const assOutlineColorInput = document.getElementById("assOutlineColor");
+const assAlignmentSelect = document.getElementById("assAlignment");
+const paragraphGapInput = document.getElementById("paragraphGap");
+const markdownTimestampsInput = document.getElementById("markdownTimestamps");
+const timestampIntervalInput = document.getElementById("timestampInterval");
+const lrcWordTimingInput = document.getElementById("lrcWordTiming");
+const shapingPresetSelect = document.getElementById("shapingPreset");
+const shapingMaxCharsInput = document.getElementById("shapingMaxChars");
+const shapingMaxLinesInput = document.getElementById("shapingMaxLines");
+const shapingMaxCpsInput = document.getElementById("shapingMaxCps");
+const retimeOffsetInput = document.getElementById("retimeOffset");
+const retimeSourceFpsInput = document.getElementById("retimeSourceFps");
+const retimeTargetFpsInput = document.getElementById("retimeTargetFps");
+const namingTemplateInput = document.getElementById("namingTemplate");
+const collisionPolicySelect = document.getElementById("collisionPolicy");
+const ffmpegPathInput = document.getElementById("ffmpegPath");
+const languageSelect = document.getElementById("language");
+const taskSelect = document.getElementById("task");
 const modelDropdown = document.getElementById('modelDropdown');
 let max_progress = 0;
 
@@ -38,7 +66,15 @@ listen('info', (event) => {
     appendConsoleMessage(event.payload);
 });
 listen('error', (event) => {
-    appendConsoleMessage(`<span style="color:red">${event.payload}</span>`);
+    const error = event.payload;
+    if (typeof error === "object" && error !== null) {
+        appendConsoleMessage(`<span style="color:red">${error.message}</span>`);
+        if (error.stderr) {
+            appendConsoleMessage(`<span style="color:red">${error.stderr}</span>`);
+        }
+    } else {
+        appendConsoleMessage(`<span style="color:red">${error}</span>`);
+    }
 });
 listen('success', (event) => {
     appendConsoleMessage(`<span style="color:green">${event.payload}</span>`);
@@ -95,6 +131,7 @@ function setGeneratingState(isGenerating) {
         buttonTextElement.innerText = "Generate Subtitle";
     }
     progressContainer.style.display = isGenerating ? "block" : "none";
+    stopTranscriptionButton.style.display = isGenerating ? "block" : "none";
 }
 
 // --- File/Folder Selection Logic ---
@@ -127,6 +164,83 @@ function hideDropdown() {
     modelDropdown.style.display = "none";
 }
 
+// Replaces the built-in list with the model registry, including models added through overrides
+async function loadModelRegistry() {
+    try {
+        const models = await invoke("list_registry_models");
+        const englishVariants = new Set(models.map((model) => model.english_variant).filter(Boolean));
+        modelDropdown.innerHTML = "";
+        models
+            .filter((model) => !englishVariants.has(model.name))
+            .forEach((model) => {
+                const item = document.createElement("div");
+                item.className = "dropdown-item";
+                item.textContent = model.name;
+                if (model.memory_mb) {
+                    item.title = `Needs about ${model.memory_mb} MB of memory`;
+                }
+                item.addEventListener("click", () => selectModel(model.name));
+                modelDropdown.appendChild(item);
+            });
+    } catch (error) {
+        console.error("Error loading model registry:", error);
+        appendConsoleMessage(`<span style="color:red">${error}</span>`);
+    }
+}
+
+// --- Output Options ---
+function selectedOutputFormats() {
+    return Array.from(outputFormatInputs)
+        .filter((input) => input.checked)
+        .map((input) => input.value)
+        .join(",");
+}
+
+function vttOptions() {
+    return JSON.stringify({
+        word_timestamps: vttWordTimestampsInput.checked,
+        align: vttAlignSelect.value || null,
+        position: vttPositionInput.value || null,
+        line: vttLineInput.value || null
+    });
+}
+
+function assStyle() {
+    return JSON.stringify({
+        karaoke: assKaraokeInput.checked,
+        font_name: assFontNameInput.value || "Arial",
+        font_size: assFontSizeInput.value ? Number(assFontSizeInput.value) : null,
+        primary_color: assPrimaryColorInput.value,
+        outline_color: assOutlineColorInput.value,
+        alignment: Number(assAlignmentSelect.value)
+    });
+}
+
+function shapingOptions() {
+    return JSON.stringify({
+        preset: shapingPresetSelect.value,
+        max_chars_per_line: shapingMaxCharsInput.value ? Number(shapingMaxCharsInput.value) : null,
+        max_lines: shapingMaxLinesInput.value ? Number(shapingMaxLinesInput.value) : null,
+        max_cps: shapingMaxCpsInput.value ? Number(shapingMaxCpsInput.value) : null
+    });
+}
+
+function retimeOptions() {
+    return JSON.stringify({
+        offset: retimeOffsetInput.value ? Number(retimeOffsetInput.value) : 0,
+        source_fps: retimeSourceFpsInput.value ? Number(retimeSourceFpsInput.value) : null,
+        target_fps: retimeTargetFpsInput.value ? Number(retimeTargetFpsInput.value) : null
+    });
+}
+
+function transcriptOptions() {
+    return JSON.stringify({
+        paragraph_gap: paragraphGapInput.value ? Number(paragraphGapInput.value) : 2,
+        markdown_timestamps: markdownTimestampsInput.checked,
+        timestamp_interval: timestampIntervalInput.value ? Number(timestampIntervalInput.value) : 60
+    });
+}
+
 // --- Event Listeners for Buttons ---
 document.getElementById("modelFile").addEventListener("click", () => selectFile(true));
 document.getElementById("selectFile").addEventListener("click", () => selectFile(false));
@@ -136,14 +250,30 @@ generateSubtitleButton.addEventListener("click", async () => {
     max_progress = 0;
     progressBar.style.width = '0%';
     consoleElement.innerHTML = ""
-    await invokeAPI("save_selection", {
-        data: [
+    // Sent along with the job so another window saving in between cannot change what this one runs
+    await invokeAPI("start_transcription", {
+        task: taskSelect.value,
+        selections: [
             { key: "model", value: modelInput.value },
             { key: "file", value: mediaFileInput.value },
-            { key: "folder", value: outputDirInput.value }
+            { key: "folder", value: outputDirInput.value },
+            { key: "formats", value: selectedOutputFormats() },
+            { key: "shaping", value: shapingOptions() },
+            { key: "retime", value: retimeOptions() },
+            { key: "vtt", value: vttOptions() },
+            { key: "ass", value: assStyle() },
+            { key: "transcript", value: transcriptOptions() },
+            { key: "lrc", value: JSON.stringify({ word_timing: lrcWordTimingInput.checked }) },
+            { key: "naming", value: namingTemplateInput.value },
+            { key: "collision", value: collisionPolicySelect.value },
+            { key: "ffmpeg", value: ffmpegPathInput.value },
+            { key: "language", value: languageSelect.value }
         ]
     });
-    await invokeAPI("start_transcription");
+});
+
+stopTranscriptionButton.addEventListener("click", async () => {
+    await invokeAPI("stop_transcription");
 });
 
 document.addEventListener('click', function (event) {
@@ -153,6 +283,7 @@ document.addEventListener('click', function (event) {
 });
 // --- Load Saved Values on DOMContentLoaded ---
 window.addEventListener('DOMContentLoaded', async () => {
+    await loadModelRegistry();
     try {
         const modelFile = await invokeAPI("load_selection", { key: "model" });
         if (modelFile) {
@@ -168,6 +299,89 @@ window.addEventListener('DOMContentLoaded', async () => {
         if (outputDir) {
             outputDirInput.value = outputDir;
         }
+
+        const formats = await invokeAPI("load_selection", { key: "formats" });
+        if (formats) {
+            const selected = formats.split(",");
+            outputFormatInputs.forEach((input) => {
+                input.checked = selected.includes(input.value);
+            });
+        }
+
+        const shaping = await invokeAPI("load_selection", { key: "shaping" });
+        if (shaping) {
+            const options = JSON.parse(shaping);
+            shapingPresetSelect.value = options.preset;
+            shapingMaxCharsInput.value = options.max_chars_per_line || "";
+            shapingMaxLinesInput.value = options.max_lines || "";
+            shapingMaxCpsInput.value = options.max_cps || "";
+        }
+
+        const retime = await invokeAPI("load_selection", { key: "retime" });
+        if (retime) {
+            const options = JSON.parse(retime);
+            retimeOffsetInput.value = options.offset || "";
+            retimeSourceFpsInput.value = options.source_fps || "";
+            retimeTargetFpsInput.value = options.target_fps || "";
+        }
+
+        const vtt = await invokeAPI("load_selection", { key: "vtt" });
+        if (vtt) {
+            const options = JSON.parse(vtt);
+            vttWordTimestampsInput.checked = options.word_timestamps;
+            vttAlignSelect.value = options.align || "";
+            vttPositionInput.value = options.position || "";
+            vttLineInput.value = options.line || "";
+        }
+
+        const ass = await invokeAPI("load_selection", { key: "ass" });
+        if (ass) {
+            const style = JSON.parse(ass);
+            assKaraokeInput.checked = style.karaoke;
+            assFontNameInput.value = style.font_name === "Arial" ? "" : style.font_name;
+            assFontSizeInput.value = style.font_size || "";
+            assPrimaryColorInput.value = style.primary_color.slice(0, 7).toLowerCase();
+            assOutlineColorInput.value = style.outline_color.slice(0, 7).toLowerCase();
+            assAlignmentSelect.value = String(style.alignment);
+        }
+
+        const transcript = await invokeAPI("load_selection", { key: "transcript" });
+        if (transcript) {
+            const options = JSON.parse(transcript);
+            paragraphGapInput.value = options.paragraph_gap === 2 ? "" : options.paragraph_gap;
+            markdownTimestampsInput.checked = options.markdown_timestamps;
+            timestampIntervalInput.value = options.timestamp_interval === 60 ? "" : options.timestamp_interval;
+        }
+
+        const lrc = await invokeAPI("load_selection", { key: "lrc" });
+        if (lrc) {
+            lrcWordTimingInput.checked = JSON.parse(lrc).word_timing;
+        }
+
+        const namingTemplate = await invokeAPI("load_selection", { key: "naming" });
+        if (namingTemplate) {
+            namingTemplateInput.value = namingTemplate;
+        }
+
+        const collisionPolicy = await invokeAPI("load_selection", { key: "collision" });
+        if (collisionPolicy) {
+            collisionPolicySelect.value = collisionPolicy;
+        }
+
+        const ffmpegPath = await invokeAPI("load_selection", { key: "ffmpeg" });
+        if (ffmpegPath) {
+            ffmpegPathInput.value = ffmpegPath;
+        }
+
+        const language = await invokeAPI("load_selection", { key: "language" });
+        if (language) {
+            languageSelect.value = language;
+        }
+
+        const task = await invokeAPI("load_selection", { key: "task" });
+        if (task) {
+            taskSelect.value = task;
+        }
     } catch (error) {
         console.error("Error loading saved selections:", error);
     }